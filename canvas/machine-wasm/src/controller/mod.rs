@@ -2,8 +2,10 @@ use machine::blocks::BlockData;
 use machine::canvas::wire::{Port, Wire};
 pub use machine::canvas::{Canvas, CanvasError};
 use machine::status::MachineStatus;
-use machine::Register::{FP, PC, SP};
-use machine::{Action, Event, Message};
+use machine::Register::{BP, FP, PC, SP};
+use machine::{Action, Event, ExecutionLimits, MemoryLayout, Message, RingBuffer, Tracer};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::to_value;
 use wasm_bindgen::prelude::*;
@@ -14,6 +16,10 @@ const NULL: JsValue = JsValue::NULL;
 pub struct Controller {
     #[wasm_bindgen(skip)]
     pub canvas: Canvas,
+
+    /// Most recent instructions of the traced machines.
+    #[wasm_bindgen(skip)]
+    pub traces: HashMap<u16, Arc<Mutex<RingBuffer>>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,6 +27,7 @@ pub struct InspectedRegister {
     pc: u16,
     sp: u16,
     fp: u16,
+    bp: u16,
 }
 
 /// Machine state returned by the inspection function.
@@ -56,6 +63,7 @@ impl Controller {
     pub fn create() -> Controller {
         Controller {
             canvas: Canvas::new(),
+            traces: HashMap::new(),
         }
     }
 
@@ -110,6 +118,15 @@ impl Controller {
         returns(self.canvas.load_program(id, source))
     }
 
+    /// Add a file that the programs can `.include`.
+    pub fn add_include_file(&mut self, path: &str, source: &str) {
+        self.canvas.seq.include_files.insert(path.into(), source.into());
+    }
+
+    pub fn remove_include_file(&mut self, path: &str) {
+        self.canvas.seq.include_files.remove(path);
+    }
+
     pub fn ready(&mut self) {
         self.canvas.seq.ready()
     }
@@ -147,6 +164,7 @@ impl Controller {
                 pc: m.reg.get(PC),
                 sp: m.reg.get(SP),
                 fp: m.reg.get(FP),
+                bp: m.reg.get(BP),
             },
             inbox_size: m.inbox.len(),
             outbox_size: m.outbox.len(),
@@ -214,12 +232,81 @@ impl Controller {
         returns(self.canvas.reset_block(id))
     }
 
+    /// Stop the machine with an error once it runs too many instructions or cycles.
+    pub fn set_execution_limits(&mut self, id: u16, fuel: Option<u32>, max_cycles: Option<u32>) -> Return {
+        let limits = ExecutionLimits::new(fuel.map(u64::from), max_cycles.map(u64::from));
+        returns(self.canvas.seq.set_limits(id, limits).map_err(|cause| CanvasError::MachineError { cause }))
+    }
+
+    /// Execution limits of the machines that are added from now on.
+    pub fn set_default_execution_limits(&mut self, fuel: Option<u32>, max_cycles: Option<u32>) {
+        self.canvas.seq.default_limits = ExecutionLimits::new(fuel.map(u64::from), max_cycles.map(u64::from));
+    }
+
+    /// How many ticks `run` runs for, before the machines that are still running are stopped.
+    pub fn set_max_run_ticks(&mut self, ticks: Option<u32>) {
+        self.canvas.seq.max_run_ticks = ticks;
+    }
+
+    /// Lay out the memory of the machine. Its program must be loaded again.
+    pub fn set_memory_layout(&mut self, id: u16, layout: MemoryLayout) -> Return {
+        returns(self.canvas.seq.set_layout(id, layout).map_err(|cause| CanvasError::MachineError { cause }))
+    }
+
+    /// Memory layout of the machines that are added from now on.
+    pub fn set_default_memory_layout(&mut self, layout: MemoryLayout) -> Return {
+        returns(self.canvas.seq.set_default_layout(layout).map_err(|cause| CanvasError::MachineError { cause }))
+    }
+
+    /// Fault when the program touches memory it should not, such as by writing over its code.
+    pub fn set_memory_protection(&mut self, id: u16, is_protected: bool) -> bool {
+        let Some(m) = self.canvas.seq.get_mut(id) else {
+            return false;
+        };
+
+        m.is_protected = is_protected;
+        true
+    }
+
     pub fn set_await_watchdog(&mut self, state: bool) {
         self.canvas.seq.await_watchdog = state;
     }
 
     pub fn clear(&mut self) {
         self.canvas = Canvas::new();
+        self.traces.clear();
+    }
+
+    /// Record the most recent instructions that the machine runs, up to the capacity.
+    pub fn enable_trace(&mut self, id: u16, capacity: usize) -> bool {
+        let Some(m) = self.canvas.seq.get_mut(id) else {
+            return false;
+        };
+
+        let buffer = Arc::new(Mutex::new(RingBuffer::new(capacity)));
+        m.tracer = Tracer::new(buffer.clone());
+        self.traces.insert(id, buffer);
+
+        true
+    }
+
+    pub fn disable_trace(&mut self, id: u16) {
+        if let Some(m) = self.canvas.seq.get_mut(id) {
+            m.tracer = Tracer::default();
+        }
+
+        self.traces.remove(&id);
+    }
+
+    /// Allows the frontend to consume the recorded instructions, from the oldest to the newest.
+    pub fn consume_trace(&mut self, id: u16) -> Return {
+        let Some(buffer) = self.traces.get(&id) else {
+            return Ok(NULL);
+        };
+
+        let records = buffer.lock().map(|mut buffer| buffer.drain()).unwrap_or_default();
+
+        Ok(to_value(&records)?)
     }
 
     /// Serialize the entire canvas state - very slow!
@@ -234,7 +321,7 @@ impl Controller {
 
         for m in canvas.seq.machines.iter_mut() {
             m.inbox.clear();
-            m.mem.buffer = vec![];
+            m.mem.reset();
             m.reg.buffer = vec![];
         }
 
@@ -47,6 +47,10 @@ export const MachineValueViewer = memo((props: Props) => {
               <span>FP</span> <strong>{registers.fp}</strong>
             </div>
 
+            <div>
+              <span>BP</span> <strong>{registers.bp}</strong>
+            </div>
+
             <div>
               <span>ID</span> <strong>{id}</strong>
             </div>
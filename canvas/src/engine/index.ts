@@ -248,7 +248,11 @@ export class CanvasEngine {
     if (status === "Running" && this.haltReason === "cycle") {
       return {
         type: "MachineError",
-        cause: { type: "ExecutionCycleExceeded", id },
+        cause: {
+          type: "ExecutionCycleExceeded",
+          id,
+          limit: { type: "RunTicks", ticks: this.maxCycle },
+        },
       }
     }
   }
@@ -3,7 +3,7 @@ import { CanvasError, MachineStatus } from "machine-wasm"
 export interface MachineState {
   error: CanvasError | null
   logs: string[]
-  registers: { pc: number; sp: number; fp: number }
+  registers: { pc: number; sp: number; fp: number; bp: number }
 
   inboxSize: number
   outboxSize: number
@@ -4,7 +4,7 @@ use proc_macro::TokenStream;
 use quote::{quote};
 use syn::{parse_macro_input, Data, DeriveInput};
 
-use crate::enums::variant_arity;
+use crate::enums::variant_words;
 
 pub fn insert_arity_method(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -13,7 +13,7 @@ pub fn insert_arity_method(input: TokenStream) -> TokenStream {
     if let Data::Enum(data_enum) = &ast.data {
         let enum_name = &ast.ident;
         let arity_values = data_enum.variants.iter().map(|variant| {
-            let field_count = variant_arity(variant);
+            let field_count = variant_words(variant);
 
             // Extract the variant's identifier.
             let variant_ident = &variant.ident;
@@ -26,6 +26,7 @@ pub fn insert_arity_method(input: TokenStream) -> TokenStream {
             }
 
             // Otherwise, match against the fields as well, e.g. `Foo::Baz(..) => 2`
+            // Each `u32` field takes up two words.
             quote! {
                 #enum_name::#variant_ident(..) => #field_count
             }
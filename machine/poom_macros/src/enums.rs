@@ -1,4 +1,4 @@
-use syn::{Variant, Fields};
+use syn::{Field, Fields, Type, Variant};
 
 /// Extract the arity of the variant fields.
 pub fn variant_arity(variant: &Variant) -> usize {
@@ -8,4 +8,25 @@ pub fn variant_arity(variant: &Variant) -> usize {
         // The variant fields does not exist. Arity is zero.
         _ => 0,
     }
-}
\ No newline at end of file
+}
+
+/// Extract the unnamed fields of the variant.
+pub fn variant_fields(variant: &Variant) -> Vec<&Field> {
+    match &variant.fields {
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        _ => vec![],
+    }
+}
+
+/// Count the words that the variant fields take up.
+pub fn variant_words(variant: &Variant) -> usize {
+    variant_fields(variant).into_iter().map(|field| if is_double_word(field) { 2 } else { 1 }).sum()
+}
+
+/// Is the field a 32-bit value? They take up two words, with the high word first.
+pub fn is_double_word(field: &Field) -> bool {
+    match &field.ty {
+        Type::Path(path) => path.path.is_ident("u32"),
+        _ => false,
+    }
+}
@@ -4,7 +4,7 @@ use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{parse_macro_input, Data, DeriveInput};
 
-use crate::enums::variant_arity;
+use crate::enums::{is_double_word, variant_arity, variant_fields};
 
 pub fn insert_field_values_method(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -31,11 +31,18 @@ pub fn insert_field_values_method(input: TokenStream) -> TokenStream {
                 quote! { #id }
             });
 
-            let field_values = field_vars.clone();
+            // The `u32` fields are split into two words, with the high word first.
+            let field_values = field_vars.clone().zip(variant_fields(variant)).map(|(var, field)| {
+                if is_double_word(field) {
+                    return quote! { (*#var >> 16) as u16, *#var as u16 };
+                }
+
+                quote! { *#var }
+            });
 
             quote! {
                 #enum_name::#variant_ident(#(#field_vars,)*) => vec![
-                    #(*#field_values,)*
+                    #(#field_values,)*
                 ]
             }
         });
@@ -4,7 +4,7 @@ use proc_macro::TokenStream;
 use quote::{quote};
 use syn::{parse_macro_input, Data, DeriveInput};
 
-use crate::enums::variant_arity;
+use crate::enums::{is_double_word, variant_arity, variant_fields};
 
 pub fn insert_arg_method(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -26,11 +26,26 @@ pub fn insert_arg_method(input: TokenStream) -> TokenStream {
 
             // Retrieve the arguments by using the arg_fn mutable closure.
             // We use Rc<RefCell>> to allow the closure to be called multiple times.
-            let args = (0..field_count).map(|_| quote! {
-                {
-                    let arg_fn = arg_fn.clone();
-                    let mut arg_fn = arg_fn.borrow_mut();
-                    arg_fn()
+            let args = variant_fields(variant).into_iter().map(|field| {
+                // The `u32` fields are made of two words, with the high word first.
+                if is_double_word(field) {
+                    return quote! {
+                        {
+                            let arg_fn = arg_fn.clone();
+                            let mut arg_fn = arg_fn.borrow_mut();
+                            let high = arg_fn() as u32;
+                            let low = arg_fn() as u32;
+                            (high << 16) | low
+                        }
+                    };
+                }
+
+                quote! {
+                    {
+                        let arg_fn = arg_fn.clone();
+                        let mut arg_fn = arg_fn.borrow_mut();
+                        arg_fn()
+                    }
                 }
             });
 
@@ -7,6 +7,7 @@ mod enum_tests {
         Pop,
         Push(u16),
         Foo(u16, u16, u16),
+        Wide(u16, u32),
     }
 
     #[test]
@@ -14,6 +15,9 @@ mod enum_tests {
         assert_eq!(Foo::Pop.with_arg(|| 12), Foo::Pop);
         assert_eq!(Foo::Push(0).with_arg(|| 24), Foo::Push(24));
         assert_eq!(Foo::Foo(0, 0, 0).with_arg(|| 6), Foo::Foo(6, 6, 6));
+
+        let mut words = [1, 0x0002, 0x0003].into_iter();
+        assert_eq!(Foo::Wide(0, 0).with_arg(|| words.next().unwrap()), Foo::Wide(1, 0x0002_0003));
     }
 
     #[test]
@@ -21,6 +25,7 @@ mod enum_tests {
         assert_eq!(Foo::Pop.arity(), 0);
         assert_eq!(Foo::Push(1).arity(), 1);
         assert_eq!(Foo::Foo(0, 0, 0).arity(), 3);
+        assert_eq!(Foo::Wide(0, 0).arity(), 3);
     }
 
     #[test]
@@ -28,6 +33,7 @@ mod enum_tests {
         assert_eq!(Foo::Pop.field_values(), []);
         assert_eq!(Foo::Push(1).field_values(), [1]);
         assert_eq!(Foo::Foo(12, 12, 12).field_values(), [12, 12, 12]);
+        assert_eq!(Foo::Wide(1, 0x0002_0003).field_values(), [1, 2, 3]);
     }
 
     #[test]
@@ -1,4 +1,6 @@
-use crate::{Op, ParseError, Parser};
+use crate::{Listing, Op, Parser, SymbolMap};
+use crate::cli::CLIError;
+use super::container::{Container, SectionKind};
 
 /// Signature of the binary file.
 pub static MAGIC_BYTES: [u16; 2] = [0xDEAD, 0xBEEF];
@@ -15,32 +17,28 @@ pub fn compile_to_bytecode(ops: Vec<Op>) -> Vec<u16> {
     bytecode
 }
 
-pub fn compile_to_binary(source: &str) -> Result<Vec<u16>, ParseError> {
-    let parser: Parser = (*source).try_into()?;
+pub fn compile_to_binary(source: &str) -> Result<Vec<u16>, CLIError> {
+    let parser: Parser = (*source).try_into().map_err(|error| CLIError::CannotParse { error })?;
 
-    // [code_start, code_size, data_start, data_size]
-    let mut header: [u16; 4] = [0x00, 0x00, 0x00, 0x00];
-
-    // Code segment.
-    let code_segment = compile_to_bytecode(parser.ops);
-    let code_start = MAGIC_BYTES.len() + header.len();
-    header[0] = code_start as u16;
-    header[1] = code_segment.len() as u16;
-
-    // Data segment.
-    let data_segment = parser.symbols.bytes();
-    let data_start = code_start + code_segment.len();
-    header[2] = data_start as u16;
-    header[3] = data_segment.len() as u16;
-
-    // Pack into binary.
-    let mut bytes = vec![];
-    bytes.extend(MAGIC_BYTES.to_vec());
-    bytes.extend(header.to_vec());
-    bytes.extend(code_segment);
-    bytes.extend(data_segment);
+    compile_parser_to_binary(parser)
+}
 
-    Ok(bytes)
+/// Compile the program that has already been parsed, e.g. with the included files.
+pub fn compile_parser_to_binary(parser: Parser) -> Result<Vec<u16>, CLIError> {
+    let symbols = SymbolMap::new(&parser.symbols, &parser.layout);
+    let listing = Listing::from(&parser);
+    let entry = parser.entry_address();
+    let layout = parser.layout.section_words();
+
+    let mut container = Container::new();
+    container.add(SectionKind::Code, compile_to_bytecode(parser.ops));
+    container.add(SectionKind::Data, parser.symbols.bytes());
+    container.add(SectionKind::Symbols, symbols.to_words());
+    container.add(SectionKind::DebugLines, listing.debug_lines());
+    container.add(SectionKind::Entry, vec![entry]);
+    container.add(SectionKind::MemoryLayout, layout);
+
+    container.to_words()
 }
 
 #[cfg(test)]
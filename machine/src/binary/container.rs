@@ -0,0 +1,197 @@
+use snafu::ensure;
+use strum_macros::FromRepr;
+use crate::cli::CLIError;
+use crate::cli::cli_error::{ChecksumMismatchSnafu, IncorrectMagicBytesSnafu, SectionOutOfRangeSnafu, TruncatedFileSnafu, UnsupportedVersionSnafu};
+use super::compile::MAGIC_BYTES;
+
+/// Version of the binary container format. Bumped whenever the sections or the opcodes change.
+pub const VERSION: u16 = 2;
+
+/// Number of words in each entry of the section table: [kind, offset, size, checksum]
+const TABLE_ENTRY_SIZE: usize = 4;
+
+/// Number of words before the section table: [magic, magic, version, section count]
+const HEADER_SIZE: usize = 4;
+
+/// Kinds of sections in the binary.
+#[derive(Debug, Copy, Clone, PartialEq, FromRepr)]
+#[repr(u16)]
+pub enum SectionKind {
+    /// Instructions, loaded into the code segment.
+    Code = 1,
+
+    /// Strings and values, loaded into the data segment.
+    Data = 2,
+
+    /// Addresses of the labels and data symbols.
+    Symbols = 3,
+
+    /// Pairs of [address, line] that map the instructions to their source lines.
+    DebugLines = 4,
+
+    /// Address of the first instruction to run.
+    Entry = 5,
+
+    /// Sizes of the six segments that the program is assembled for, in layout order:
+    /// [code, data, mapped, call stack, stack, heap]
+    MemoryLayout = 6,
+
+    /// Names of the symbols that an object file imports from other object files.
+    Imports = 7,
+
+    /// Words in an object file that the linker adjusts. Executables do not have this section.
+    Relocations = 8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    /// Kind of the section. Unknown kinds from newer versions are kept as-is.
+    pub kind: u16,
+
+    pub words: Vec<u16>,
+}
+
+/// Versioned binary container with a section table.
+///
+/// ```text
+/// [0xDEAD, 0xBEEF, version, section count]
+/// [kind, offset, size, checksum] for each section
+/// section contents
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Container {
+    pub sections: Vec<Section>,
+}
+
+impl Container {
+    pub fn new() -> Container {
+        Container { sections: vec![] }
+    }
+
+    /// Add a section to the container.
+    pub fn add(&mut self, kind: SectionKind, words: Vec<u16>) {
+        self.sections.push(Section { kind: kind as u16, words });
+    }
+
+    /// Returns the contents of the first section of the kind.
+    pub fn section(&self, kind: SectionKind) -> Option<&[u16]> {
+        self.sections.iter()
+            .find(|s| s.kind == kind as u16)
+            .map(|s| s.words.as_slice())
+    }
+
+    /// Pack the container into a binary.
+    /// The section table holds 16-bit offsets and sizes, so each section has to start within the first 64K words.
+    pub fn to_words(&self) -> Result<Vec<u16>, CLIError> {
+        let mut bytes = vec![];
+        bytes.extend(MAGIC_BYTES);
+        bytes.push(VERSION);
+        bytes.push(self.sections.len() as u16);
+
+        // Section contents are placed right after the section table.
+        let mut offset = HEADER_SIZE + self.sections.len() * TABLE_ENTRY_SIZE;
+
+        for section in &self.sections {
+            let size = section.words.len();
+
+            let (Ok(start), Ok(len)) = (u16::try_from(offset), u16::try_from(size)) else {
+                return SectionOutOfRangeSnafu { kind: section.kind, offset, size }.fail();
+            };
+
+            bytes.extend([section.kind, start, len, checksum(&section.words)]);
+            offset += size;
+        }
+
+        for section in &self.sections {
+            bytes.extend(&section.words);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Unpack the binary, verifying the header, the section bounds and the checksums.
+    pub fn from_words(bytes: &[u16]) -> Result<Container, CLIError> {
+        ensure!(bytes.len() >= HEADER_SIZE, TruncatedFileSnafu);
+
+        // Verify magic bytes at the beginning of file.
+        ensure!(bytes[0..2] == MAGIC_BYTES, IncorrectMagicBytesSnafu);
+
+        let version = bytes[2];
+        ensure!(version == VERSION, UnsupportedVersionSnafu { version });
+
+        let count = bytes[3] as usize;
+        let table = bytes.get(HEADER_SIZE..(HEADER_SIZE + count * TABLE_ENTRY_SIZE)).ok_or(CLIError::TruncatedFile)?;
+
+        let mut sections = vec![];
+
+        for entry in table.chunks(TABLE_ENTRY_SIZE) {
+            let [kind, offset, size, sum] = entry else { return Err(CLIError::IncorrectFileHeader); };
+
+            let start = *offset as usize;
+            let words = bytes.get(start..(start + *size as usize)).ok_or(CLIError::TruncatedFile)?;
+
+            ensure!(checksum(words) == *sum, ChecksumMismatchSnafu { kind: *kind });
+
+            sections.push(Section { kind: *kind, words: words.to_vec() });
+        }
+
+        Ok(Container { sections })
+    }
+}
+
+/// CRC-16/CCITT-FALSE checksum over the bytes of the words.
+/// Unlike a Fletcher checksum modulo 255, it tells the 0x00 and 0xFF bytes apart.
+pub fn checksum(words: &[u16]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for byte in words.iter().flat_map(|w| w.to_be_bytes()) {
+        crc ^= (byte as u16) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(&[]), 0xFFFF);
+        assert_eq!(checksum(&[0x3132, 0x3334, 0x3536, 0x3738]), 0xA12B, "CRC of the ASCII bytes of 12345678");
+
+        // Swapping the words changes the checksum.
+        assert_ne!(checksum(&[0x0102, 0x0304]), checksum(&[0x0304, 0x0102]));
+
+        // Fletcher-16 modulo 255 cannot tell these apart.
+        assert_ne!(checksum(&[0x0000]), checksum(&[0x00FF]));
+    }
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let mut container = Container::new();
+        container.add(SectionKind::Code, vec![1, 2, 3]);
+        container.add(SectionKind::Entry, vec![0]);
+
+        let bytes = container.to_words().expect("cannot pack the container");
+        assert_eq!(bytes[0..4], [0xDEAD, 0xBEEF, VERSION, 2]);
+
+        let unpacked = Container::from_words(&bytes).expect("cannot unpack the container");
+        assert_eq!(unpacked, container);
+        assert_eq!(unpacked.section(SectionKind::Code), Some(&[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn test_section_out_of_range() {
+        let mut container = Container::new();
+        container.add(SectionKind::Code, vec![0; 0xFFFF]);
+        container.add(SectionKind::Data, vec![1]);
+
+        // The data section starts past the 16-bit offsets.
+        assert!(matches!(container.to_words(), Err(CLIError::SectionOutOfRange { kind: 2, .. })));
+    }
+}
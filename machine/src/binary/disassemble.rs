@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use crate::{MemoryLayout, Op};
+use super::disassemble_error::DisassembleError;
+use super::disassemble_error::DisassembleError::{MissingEndOfFile, TruncatedInstruction, UnknownOpcode};
+
+/// Data definition decoded from the data segment.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataItem {
+    /// Null-terminated string, written as `.string`
+    String(String),
+
+    /// Single word, written as `.value`
+    Value(u16),
+
+    /// Run of zeros, written as `.zero`
+    Zero(u16),
+}
+
+impl DataItem {
+    /// Number of words the item occupies in the data segment.
+    pub fn size(&self) -> u16 {
+        match self {
+            DataItem::String(text) => text.encode_utf16().count() as u16 + 1,
+            DataItem::Value(_) => 1,
+            DataItem::Zero(size) => *size,
+        }
+    }
+}
+
+/// Reconstruct the assembly source code from the code and data segments of a binary.
+/// Assembling the source code for the same memory layout produces the exact same segments.
+pub fn disassemble(code: &[u16], data: &[u16], layout: &MemoryLayout) -> Result<String, DisassembleError> {
+    let ops = decode(code)?;
+    let items = decode_data(data);
+
+    // Every data definition is named after its offset.
+    let names: BTreeMap<u16, String> = items.iter()
+        .map(|(offset, _)| (*offset, format!("data_{}", offset)))
+        .collect();
+
+    // Jump and call targets on instruction boundaries are replaced with labels.
+    // The end of the code segment is also a valid target.
+    let end = code.len() as u16 - 1;
+
+    let labels: BTreeMap<u16, String> = ops.iter()
+        .filter_map(|(_, op)| jump_target(op))
+        .filter(|target| *target == end || ops.iter().any(|(offset, _)| offset == target))
+        .map(|target| (target, format!("label_{}", target)))
+        .collect();
+
+    let mut lines: Vec<String> = vec![];
+
+    for (offset, item) in items.iter() {
+        let name = &names[offset];
+
+        lines.push(match item {
+            DataItem::String(text) => format!(".string {} \"{}\"", name, text),
+            DataItem::Value(value) => format!(".value {} 0x{:04X}", name, value),
+            DataItem::Zero(size) => format!(".zero {} {}", name, size),
+        });
+    }
+
+    if !lines.is_empty() {
+        lines.push("".into());
+    }
+
+    for (offset, op) in ops.iter() {
+        if let Some(label) = labels.get(offset) {
+            lines.push(format!("{}:", label));
+        }
+
+        lines.push(instruction(op, &labels, &names, layout));
+    }
+
+    if let Some(label) = labels.get(&end) {
+        lines.push(format!("{}:", label));
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Decode the code segment into instructions and their offsets.
+/// The end-of-file marker at the end of the segment is not included.
+pub fn decode(code: &[u16]) -> Result<Vec<(u16, Op)>, DisassembleError> {
+    let mut ops = vec![];
+    let mut pc = 0;
+
+    while pc < code.len() {
+        let offset = pc as u16;
+        let opcode = code[pc];
+
+        let op = Op::from_repr(opcode).ok_or(UnknownOpcode { opcode, offset })?;
+        let arity = op.arity();
+
+        let args = code.get((pc + 1)..(pc + 1 + arity)).ok_or(TruncatedInstruction { offset })?;
+        let mut args = args.iter();
+
+        ops.push((offset, op.with_arg(|| args.next().copied().unwrap_or(0))));
+        pc += arity + 1;
+    }
+
+    // The compiler always appends the end-of-file marker.
+    match ops.pop() {
+        Some((_, Op::Eof)) => Ok(ops),
+        _ => Err(MissingEndOfFile),
+    }
+}
+
+/// Decode the data segment into strings, values and runs of zeros, along with their offsets.
+pub fn decode_data(data: &[u16]) -> Vec<(u16, DataItem)> {
+    let mut items = vec![];
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let rest = &data[offset..];
+
+        // Printable text followed by the null terminator.
+        let text_len = text_len(rest);
+        let zeros = rest.iter().take_while(|w| **w == 0).count();
+
+        let item = if text_len > 0 && rest.get(text_len) == Some(&0) {
+            DataItem::String(String::from_utf16_lossy(&rest[..text_len]))
+        } else if zeros > 1 {
+            DataItem::Zero(zeros as u16)
+        } else {
+            DataItem::Value(rest[0])
+        };
+
+        let size = item.size();
+        items.push((offset as u16, item));
+        offset += size as usize;
+    }
+
+    items
+}
+
+/// Can the word be written inside a string literal?
+fn is_printable(word: u16) -> bool {
+    (0x20..=0x7E).contains(&word) && word != '"' as u16
+}
+
+/// Number of words of printable UTF-16 text at the start of the data.
+/// The text has to start with an ASCII character, so other values are not mistaken for text.
+fn text_len(data: &[u16]) -> usize {
+    if !data.first().is_some_and(|w| is_printable(*w)) { return 0; }
+
+    char::decode_utf16(data.iter().copied())
+        .map_while(|c| c.ok().filter(|c| !c.is_control() && *c != '"'))
+        .map(char::len_utf16)
+        .sum()
+}
+
+fn jump_target(op: &Op) -> Option<u16> {
+    match *op {
+        Op::Jump(target) | Op::JumpZero(target) | Op::JumpNotZero(target) | Op::Call(target) => Some(target),
+        _ => None,
+    }
+}
+
+fn instruction(op: &Op, labels: &BTreeMap<u16, String>, names: &BTreeMap<u16, String>, layout: &MemoryLayout) -> String {
+    let mut operands: Vec<String> = op.field_values().iter().map(|v| v.to_string()).collect();
+
+    // Refer to the labels and data definitions by name.
+    let name = match *op {
+        Op::Load(address) | Op::Store(address) | Op::LoadString(address) => {
+            address.checked_sub(layout.data_start())
+                .and_then(|offset| names.get(&offset))
+                .map(|name| format!("&{}", name))
+        }
+
+        _ => jump_target(op).and_then(|target| labels.get(&target).cloned()),
+    };
+
+    if let Some(name) = name {
+        operands = vec![name];
+    }
+
+    let mut text = op.to_string();
+
+    for operand in operands {
+        text += " ";
+        text += &operand;
+    }
+
+    text
+}
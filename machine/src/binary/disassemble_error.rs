@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use tsify::Tsify;
+
+#[derive(Debug, Snafu, Serialize, Deserialize, PartialEq, Clone, Tsify)]
+#[snafu(visibility(pub))]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi, namespace)]
+pub enum DisassembleError {
+    #[snafu(display("unknown opcode {opcode} at offset {offset}"))]
+    UnknownOpcode { opcode: u16, offset: u16 },
+
+    #[snafu(display("instruction at offset {offset} is missing its operands"))]
+    TruncatedInstruction { offset: u16 },
+
+    #[snafu(display("code segment does not end with the end-of-file marker"))]
+    MissingEndOfFile,
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use snafu::{ensure, OptionExt};
+use crate::{MemoryLayout, ObjectFile, Op, RelocationTarget, Segment, SymbolEntry, SymbolMap};
+use super::container::{Container, SectionKind};
+use super::link_error::{DuplicateEntrySnafu, DuplicateSymbolSnafu, InvalidRelocationSnafu, LayoutMismatchSnafu, LinkError, NoObjectFilesSnafu, SegmentOverflowSnafu};
+use super::link_error::LinkError::{UndefinedEntry, UndefinedSymbol};
+
+/// Link the object files into an executable.
+///
+/// The code and data segments of the object files are laid out one after another, in the given order.
+/// The program starts from the `entry` label if given, or else from the entry point declared with `.entry`,
+/// or else from the first instruction of the first object file.
+///
+/// The object files must be assembled for the same memory layout, which the executable is linked for.
+pub fn link(objects: &[ObjectFile], entry: Option<&str>) -> Result<Vec<u16>, LinkError> {
+    let layout = objects.first().map(|object| object.layout).context(NoObjectFilesSnafu)?;
+    ensure!(objects.iter().all(|object| object.layout == layout), LayoutMismatchSnafu);
+
+    let mut code: Vec<u16> = vec![];
+    let mut data: Vec<u16> = vec![];
+
+    // Where the code and data segments of each object file start.
+    let mut bases: Vec<(u16, u16)> = vec![];
+
+    for object in objects {
+        bases.push((code.len() as u16, data.len() as u16));
+
+        // Only the last object file keeps its end-of-file marker.
+        code.extend(object.code.iter().take(object.code.len().saturating_sub(1)));
+        data.extend(&object.data);
+    }
+
+    code.push(Op::Eof.opcode());
+
+    ensure!(layout.check_program(code.len(), data.len()).is_ok(), SegmentOverflowSnafu);
+
+    // Exported symbols, at their final addresses.
+    let mut symbols: HashMap<String, SymbolEntry> = HashMap::new();
+
+    for (object, (code_base, data_base)) in objects.iter().zip(bases.iter()) {
+        for entry in &object.exports.entries {
+            let relocated = match entry.clone() {
+                SymbolEntry::Label { name, address } => SymbolEntry::Label { name, address: address.wrapping_add(*code_base) },
+                SymbolEntry::Data { name, address, size } => SymbolEntry::Data { name, address: address.wrapping_add(*data_base), size },
+            };
+
+            let name = entry.name().to_owned();
+            ensure!(!symbols.contains_key(&name), DuplicateSymbolSnafu { name });
+
+            symbols.insert(name, relocated);
+        }
+    }
+
+    for (object, (code_base, data_base)) in objects.iter().zip(bases.iter()) {
+        for relocation in &object.relocations {
+            let adjustment = match &relocation.target {
+                RelocationTarget::Code => *code_base,
+                RelocationTarget::Data => *data_base,
+                RelocationTarget::Import(name) => {
+                    symbols.get(name).ok_or(UndefinedSymbol { name: name.clone() })?.address()
+                }
+            };
+
+            let (segment, base, size) = match relocation.segment {
+                Segment::Code => (&mut code, *code_base, object.code.len()),
+                Segment::Data => (&mut data, *data_base, object.data.len()),
+            };
+
+            let offset = relocation.offset;
+            ensure!((offset as usize) < size, InvalidRelocationSnafu { offset });
+
+            let word = &mut segment[(base + offset) as usize];
+            *word = word.wrapping_add(adjustment);
+        }
+    }
+
+    let entry = entry_point(objects, &bases, &symbols, entry, &layout)?;
+
+    let mut exports: Vec<SymbolEntry> = symbols.into_values().collect();
+    exports.sort_by_key(|e| (matches!(e, SymbolEntry::Data { .. }), e.address(), e.name().to_owned()));
+
+    let mut container = Container::new();
+    container.add(SectionKind::Code, code);
+    container.add(SectionKind::Data, data);
+    container.add(SectionKind::Symbols, SymbolMap { entries: exports }.to_words());
+    container.add(SectionKind::Entry, vec![entry]);
+    container.add(SectionKind::MemoryLayout, layout.section_words());
+
+    container.to_words().map_err(|_| LinkError::BinaryTooLarge)
+}
+
+fn entry_point(objects: &[ObjectFile], bases: &[(u16, u16)], symbols: &HashMap<String, SymbolEntry>, entry: Option<&str>, layout: &MemoryLayout) -> Result<u16, LinkError> {
+    if let Some(name) = entry {
+        return match symbols.get(name) {
+            Some(SymbolEntry::Label { address, .. }) => Ok(*address),
+            _ => Err(UndefinedEntry { name: name.into() }),
+        };
+    }
+
+    let declared: Vec<u16> = objects.iter().zip(bases.iter())
+        .filter_map(|(object, (code_base, _))| object.entry.map(|entry| entry.wrapping_add(*code_base)))
+        .collect();
+
+    ensure!(declared.len() <= 1, DuplicateEntrySnafu);
+
+    Ok(declared.first().copied().unwrap_or(layout.code_start()))
+}
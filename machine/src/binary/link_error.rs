@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use tsify::Tsify;
+
+#[derive(Debug, Snafu, Serialize, Deserialize, PartialEq, Clone, Tsify)]
+#[snafu(visibility(pub))]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi, namespace)]
+pub enum LinkError {
+    #[snafu(display("no object files to link"))]
+    NoObjectFiles,
+
+    #[snafu(display("symbol '{name}' is imported, but no object file exports it"))]
+    UndefinedSymbol { name: String },
+
+    #[snafu(display("symbol '{name}' is exported by more than one object file"))]
+    DuplicateSymbol { name: String },
+
+    #[snafu(display("entry point '{name}' is not an exported label"))]
+    UndefinedEntry { name: String },
+
+    #[snafu(display("more than one object file declares an entry point"))]
+    DuplicateEntry,
+
+    #[snafu(display("relocation at offset {offset} is outside of its segment"))]
+    InvalidRelocation { offset: u16 },
+
+    #[snafu(display("object files are assembled for different memory layouts"))]
+    LayoutMismatch,
+
+    #[snafu(display("linked program does not fit in the memory segments"))]
+    SegmentOverflow,
+
+    #[snafu(display("linked program is too large for the section table of the binary"))]
+    BinaryTooLarge,
+}
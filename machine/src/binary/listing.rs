@@ -0,0 +1,142 @@
+use std::fmt;
+use crate::{Parser, SourceLookup, CODE_START};
+
+/// Assembled instruction with its location in the source code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListingEntry {
+    /// Address of the instruction in memory.
+    pub address: u16,
+
+    /// Encoded opcode and operands.
+    pub words: Vec<u16>,
+
+    /// Line number in the source code.
+    pub line: usize,
+
+    /// Included file of the source line. None if it is in the main source code.
+    pub file: Option<String>,
+
+    /// Text of the source line.
+    pub source: String,
+}
+
+/// Maps each code address to the source line it is assembled from.
+///
+/// ```text
+/// 0000  0001 000A        1 | push 10
+/// 0002  0001 0003        2 | push 3
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Listing {
+    pub entries: Vec<ListingEntry>,
+}
+
+impl Listing {
+    /// Returns the instruction that starts at the address.
+    pub fn entry_at(&self, address: u16) -> Option<&ListingEntry> {
+        self.entries.iter().find(|e| e.address == address)
+    }
+
+    pub fn render(&self) -> String {
+        self.entries.iter().map(|e| format!("{}\n", e)).collect()
+    }
+
+    /// Encode into the debug lines section of the binary, as `[address, line]` pairs.
+    pub fn debug_lines(&self) -> Vec<u16> {
+        self.entries.iter().flat_map(|e| [e.address, e.line as u16]).collect()
+    }
+
+    /// Rebuild the listing from the debug lines section and the code segment of a binary.
+    /// The source text is not stored in the binary, so only the line numbers are shown.
+    pub fn from_debug_lines(lines: &[u16], code: &[u16]) -> Listing {
+        let pairs: Vec<(u16, u16)> = lines.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+
+        let entries = pairs.iter().enumerate().map(|(i, (address, line))| {
+            // Each instruction spans up to the start of the next one.
+            let start = address.saturating_sub(CODE_START) as usize;
+            let end = pairs.get(i + 1).map_or(code.len().saturating_sub(1), |(next, _)| next.saturating_sub(CODE_START) as usize);
+
+            ListingEntry {
+                address: *address,
+                words: code.get(start..end).unwrap_or_default().to_vec(),
+                line: *line as usize,
+                file: None,
+                source: "".into(),
+            }
+        }).collect();
+
+        Listing { entries }
+    }
+
+    /// Parse the listing file rendered by `render`.
+    pub fn parse(text: &str) -> Listing {
+        let entries = text.lines().filter_map(|line| {
+            let (head, source) = line.split_once(" | ")?;
+            let mut fields: Vec<&str> = head.split_whitespace().collect();
+
+            // The source location is the last field, e.g. "12" or "lib/math.asm:12"
+            let location = fields.pop()?;
+
+            let (file, line) = match location.rsplit_once(':') {
+                Some((file, line)) => (Some(file.to_owned()), line),
+                None => (None, location),
+            };
+
+            let (address, words) = fields.split_first()?;
+
+            Some(ListingEntry {
+                address: u16::from_str_radix(address, 16).ok()?,
+                words: words.iter().filter_map(|w| u16::from_str_radix(w, 16).ok()).collect(),
+                line: line.parse().ok()?,
+                file,
+                source: source.into(),
+            })
+        }).collect();
+
+        Listing { entries }
+    }
+}
+
+impl From<&Parser> for Listing {
+    fn from(parser: &Parser) -> Self {
+        let mut address = parser.layout.code_start();
+        let mut entries = vec![];
+
+        for (op, span) in parser.ops.iter().zip(parser.spans.iter()) {
+            let mut words = vec![op.opcode()];
+            words.extend(op.field_values());
+
+            let source = parser.source_of(span.file.as_deref())
+                .and_then(|source| source.lines().nth(span.line.saturating_sub(1)))
+                .unwrap_or("")
+                .trim();
+
+            let size = words.len() as u16;
+
+            entries.push(ListingEntry {
+                address,
+                words,
+                line: span.line,
+                file: span.file.clone(),
+                source: source.into(),
+            });
+
+            address += size;
+        }
+
+        Listing { entries }
+    }
+}
+
+impl fmt::Display for ListingEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words: Vec<String> = self.words.iter().map(|w| format!("{:04X}", w)).collect();
+
+        let location = match &self.file {
+            Some(file) => format!("{}:{}", file, self.line),
+            None => self.line.to_string(),
+        };
+
+        write!(f, "{:04X}  {:<14} {:>4} | {}", self.address, words.join(" "), location, self.source)
+    }
+}
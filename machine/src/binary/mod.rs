@@ -1,6 +1,22 @@
 pub mod run;
 pub mod compile;
 pub mod bytes;
+pub mod container;
+pub mod disassemble;
+pub mod disassemble_error;
+pub mod listing;
+pub mod symbol_map;
+pub mod object;
+pub mod link;
+pub mod link_error;
 
 pub use run::*;
 pub use compile::*;
+pub use container::{Container, Section, SectionKind};
+pub use disassemble::*;
+pub use disassemble_error::DisassembleError;
+pub use listing::*;
+pub use symbol_map::*;
+pub use object::ObjectFile;
+pub use link::link;
+pub use link_error::LinkError;
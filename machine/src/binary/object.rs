@@ -0,0 +1,145 @@
+use snafu::ensure;
+use crate::{MemoryLayout, Parser, Relocation, RelocationTarget, Segment, SymbolMap};
+use crate::cli::CLIError;
+use crate::cli::cli_error::{CorruptSectionSnafu, NotObjectFileSnafu};
+use super::compile::compile_to_bytecode;
+use super::container::{Container, SectionKind};
+use super::run::{layout_of, require_section};
+
+/// Relocatable object file, which is linked with other object files into an executable.
+///
+/// Addresses are laid out as if the file is loaded at the start of the code and data segments.
+/// The relocations tell the linker which words to adjust once the files are laid out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjectFile {
+    /// Instructions, ending with the end-of-file marker.
+    pub code: Vec<u16>,
+
+    pub data: Vec<u16>,
+
+    /// Labels and data visible to other object files.
+    pub exports: SymbolMap,
+
+    /// Symbols defined in other object files.
+    pub imports: Vec<String>,
+
+    pub relocations: Vec<Relocation>,
+
+    /// Offset of the entry point in the code segment, if the file declares one.
+    pub entry: Option<u16>,
+
+    /// Memory layout that the file is assembled for. Only files with the same layout can be linked together.
+    pub layout: MemoryLayout,
+}
+
+impl ObjectFile {
+    /// Pack the object file into a container.
+    pub fn to_words(&self) -> Result<Vec<u16>, CLIError> {
+        let mut container = Container::new();
+        container.add(SectionKind::Code, self.code.clone());
+        container.add(SectionKind::Data, self.data.clone());
+        container.add(SectionKind::Symbols, self.exports.to_words());
+        container.add(SectionKind::Imports, encode_names(&self.imports));
+        container.add(SectionKind::Relocations, self.encode_relocations());
+        container.add(SectionKind::MemoryLayout, self.layout.section_words());
+
+        if let Some(entry) = self.entry {
+            container.add(SectionKind::Entry, vec![entry]);
+        }
+
+        container.to_words()
+    }
+
+    /// Unpack the object file. Executables are rejected, as they cannot be relocated.
+    pub fn from_words(bytes: &[u16]) -> Result<ObjectFile, CLIError> {
+        let container = Container::from_words(bytes)?;
+        ensure!(container.section(SectionKind::Relocations).is_some(), NotObjectFileSnafu);
+
+        let corrupt = |kind: SectionKind| CorruptSectionSnafu { kind: kind as u16 };
+
+        let exports = require_section(&container, SectionKind::Symbols)?;
+        let imports = require_section(&container, SectionKind::Imports)?;
+        let relocations = require_section(&container, SectionKind::Relocations)?;
+
+        let imports = decode_names(imports).ok_or_else(|| corrupt(SectionKind::Imports).build())?;
+
+        Ok(ObjectFile {
+            code: require_section(&container, SectionKind::Code)?.to_vec(),
+            data: require_section(&container, SectionKind::Data)?.to_vec(),
+            exports: SymbolMap::from_words(exports).ok_or_else(|| corrupt(SectionKind::Symbols).build())?,
+            relocations: decode_relocations(relocations, &imports).ok_or_else(|| corrupt(SectionKind::Relocations).build())?,
+            imports,
+            entry: container.section(SectionKind::Entry).and_then(|entry| entry.first().copied()),
+            layout: layout_of(&container)?,
+        })
+    }
+
+    /// Each relocation is `[segment, offset, target, import]`.
+    /// The target is 0 for code, 1 for data and 2 for imports, which refer to the index of the import.
+    fn encode_relocations(&self) -> Vec<u16> {
+        self.relocations.iter().flat_map(|r| {
+            let (target, import) = match &r.target {
+                RelocationTarget::Code => (0, 0),
+                RelocationTarget::Data => (1, 0),
+                RelocationTarget::Import(name) => (2, self.imports.iter().position(|i| i == name).unwrap_or(0) as u16),
+            };
+
+            [r.segment as u16, r.offset, target, import]
+        }).collect()
+    }
+}
+
+impl From<Parser> for ObjectFile {
+    fn from(parser: Parser) -> Self {
+        let symbols = SymbolMap::new(&parser.symbols, &parser.layout);
+
+        let exports = symbols.entries.into_iter()
+            .filter(|entry| parser.exports.iter().any(|name| name == entry.name()))
+            .collect();
+
+        ObjectFile {
+            entry: parser.entry.as_ref().map(|_| parser.entry_address()),
+            data: parser.symbols.bytes(),
+            code: compile_to_bytecode(parser.ops),
+            exports: SymbolMap { entries: exports },
+            imports: parser.imports,
+            relocations: parser.relocations,
+            layout: parser.layout,
+        }
+    }
+}
+
+fn decode_relocations(words: &[u16], imports: &[String]) -> Option<Vec<Relocation>> {
+    words.chunks(4).map(|chunk| {
+        let [segment, offset, target, import] = *chunk else { return None; };
+
+        let target = match target {
+            0 => RelocationTarget::Code,
+            1 => RelocationTarget::Data,
+            2 => RelocationTarget::Import(imports.get(import as usize)?.clone()),
+            _ => return None,
+        };
+
+        Some(Relocation { segment: Segment::from_repr(segment)?, offset, target })
+    }).collect()
+}
+
+/// Each name is `[length, UTF-16 words...]`
+fn encode_names(names: &[String]) -> Vec<u16> {
+    names.iter().flat_map(|name| {
+        std::iter::once(name.encode_utf16().count() as u16).chain(name.encode_utf16())
+    }).collect()
+}
+
+fn decode_names(words: &[u16]) -> Option<Vec<String>> {
+    let mut names = vec![];
+    let mut rest = words;
+
+    while let Some((len, tail)) = rest.split_first() {
+        let chars = tail.get(..(*len as usize))?;
+        names.push(String::from_utf16(chars).ok()?);
+        rest = &tail[(*len as usize)..];
+    }
+
+    Some(names)
+}
@@ -1,28 +1,78 @@
-use snafu::ensure;
-use crate::{Machine, CODE_START, DATA_START};
-use crate::cli::cli_error::{IncorrectMagicBytesSnafu};
+use snafu::{ensure, OptionExt};
+use crate::{Machine, MemoryLayout};
+use crate::Register::PC;
+use crate::cli::cli_error::{CorruptSectionSnafu, IncompatibleMemoryLayoutSnafu, MissingSectionSnafu, NotExecutableSnafu};
 use crate::cli::CLIError;
-use crate::cli::CLIError::IncorrectFileHeader;
-use super::compile::MAGIC_BYTES;
+use super::container::{Container, SectionKind};
 
+/// Load the binary into a machine, with the memory layout that the binary is assembled for.
 pub fn load_from_binary(bytes: &[u16]) -> Result<Machine, CLIError> {
-    // Verify magic bytes at the beginning of file.
-    ensure!(bytes[0..2] == MAGIC_BYTES[0..2], IncorrectMagicBytesSnafu);
+    let container = Container::from_words(bytes)?;
+    let layout = layout_of(&container)?;
 
-    let header: Vec<usize> = bytes[2..6].iter().map(|&x| x as usize).collect();
+    load_container(&container, layout)
+}
+
+/// Load the binary into a machine with the memory layout. The binary must be assembled for the same layout.
+pub fn load_from_binary_with_layout(bytes: &[u16], layout: MemoryLayout) -> Result<Machine, CLIError> {
+    let container = Container::from_words(bytes)?;
+    ensure!(layout_of(&container)? == layout, IncompatibleMemoryLayoutSnafu);
 
-    // Read header from binary.
-    let [code_ptr, code_len, data_ptr, data_len] = header[..] else {
-        return Err(IncorrectFileHeader);
-    };
+    load_container(&container, layout)
+}
 
-    // Read segments from binary.
-    let code_bytes = bytes[code_ptr..(code_ptr + code_len)].to_vec();
-    let data_bytes = bytes[data_ptr..(data_ptr + data_len)].to_vec();
+fn load_container(container: &Container, layout: MemoryLayout) -> Result<Machine, CLIError> {
+    let (code_bytes, data_bytes) = segments_of(container, &layout)?;
+
+    // The program starts from the entry point, which must be inside the code segment.
+    let entry = require_section(container, SectionKind::Entry)?.first().copied();
+    let entry = entry.filter(|e| (*e as usize) < code_bytes.len()).context(CorruptSectionSnafu { kind: SectionKind::Entry as u16 })?;
 
     // Load the segments into memory.
-    let mut m = Machine::new();
-    m.mem.write(CODE_START, &code_bytes);
-    m.mem.write(DATA_START, &data_bytes);
+    let mut m = Machine::with_layout(layout).map_err(|_| CLIError::IncompatibleMemoryLayout)?;
+    m.mem.write(layout.code_start(), &code_bytes);
+    m.mem.write(layout.data_start(), &data_bytes);
+    m.entry = entry;
+    m.reg.set(PC, entry);
     Ok(m)
 }
+
+/// Read the code and data segments from the binary.
+pub fn read_segments(bytes: &[u16]) -> Result<(Vec<u16>, Vec<u16>), CLIError> {
+    let container = Container::from_words(bytes)?;
+    let layout = layout_of(&container)?;
+
+    segments_of(&container, &layout)
+}
+
+fn segments_of(container: &Container, layout: &MemoryLayout) -> Result<(Vec<u16>, Vec<u16>), CLIError> {
+    // Object files must be linked before they can run.
+    ensure!(container.section(SectionKind::Relocations).is_none(), NotExecutableSnafu);
+
+    let code_bytes = require_section(container, SectionKind::Code)?;
+    let data_bytes = require_section(container, SectionKind::Data)?;
+
+    ensure!(layout.check_program(code_bytes.len(), data_bytes.len()).is_ok(), IncompatibleMemoryLayoutSnafu);
+
+    Ok((code_bytes.to_vec(), data_bytes.to_vec()))
+}
+
+pub fn require_section(container: &Container, kind: SectionKind) -> Result<&[u16], CLIError> {
+    container.section(kind).context(MissingSectionSnafu { kind: kind as u16 })
+}
+
+/// Read the memory layout that the binary is assembled for.
+pub fn read_memory_layout(bytes: &[u16]) -> Result<MemoryLayout, CLIError> {
+    layout_of(&Container::from_words(bytes)?)
+}
+
+/// Memory layout that the program is assembled for, which must be valid.
+pub(super) fn layout_of(container: &Container) -> Result<MemoryLayout, CLIError> {
+    let corrupt = CorruptSectionSnafu { kind: SectionKind::MemoryLayout as u16 };
+
+    let section = require_section(container, SectionKind::MemoryLayout)?;
+    let layout = MemoryLayout::from_section_words(section).context(corrupt)?;
+    ensure!(layout.validate().is_ok(), corrupt);
+
+    Ok(layout)
+}
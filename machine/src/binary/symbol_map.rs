@@ -0,0 +1,113 @@
+use std::fmt;
+use crate::{MemoryLayout, Symbols};
+
+/// Address of a symbol in memory.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SymbolEntry {
+    /// Label in the code segment.
+    Label { name: String, address: u16 },
+
+    /// Strings, values and arrays in the data segment.
+    Data { name: String, address: u16, size: u16 },
+}
+
+/// Maps each label and data symbol to its address.
+///
+/// ```text
+/// label start  0x0009
+/// data  msg    0x1000 14
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SymbolMap {
+    /// Symbols sorted by their address, with the labels first.
+    pub entries: Vec<SymbolEntry>,
+}
+
+impl SymbolEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            SymbolEntry::Label { name, .. } | SymbolEntry::Data { name, .. } => name,
+        }
+    }
+
+    pub fn address(&self) -> u16 {
+        match self {
+            SymbolEntry::Label { address, .. } | SymbolEntry::Data { address, .. } => *address,
+        }
+    }
+}
+
+impl SymbolMap {
+    /// Map the symbols to their addresses in the memory layout that the program is assembled for.
+    pub fn new(symbols: &Symbols, layout: &MemoryLayout) -> SymbolMap {
+        let mut entries: Vec<SymbolEntry> = symbols.offsets.iter().map(|(name, offset)| {
+            let name = name.clone();
+
+            match symbols.size(&name) {
+                Some(size) => SymbolEntry::Data { name, address: layout.data_start() + offset, size },
+                None => SymbolEntry::Label { name, address: layout.code_start() + offset },
+            }
+        }).collect();
+
+        entries.sort_by_key(|e| match e {
+            SymbolEntry::Label { name, address } => (0, *address, name.clone()),
+            SymbolEntry::Data { name, address, .. } => (1, *address, name.clone()),
+        });
+
+        SymbolMap { entries }
+    }
+
+    pub fn render(&self) -> String {
+        self.entries.iter().map(|e| format!("{}\n", e)).collect()
+    }
+
+    /// Encode into the symbols section of the binary.
+    /// Each entry is `[kind, address, size, name length, name...]`, with kind 0 for labels and 1 for data.
+    /// The names are stored as UTF-16, the same as strings.
+    pub fn to_words(&self) -> Vec<u16> {
+        let mut words = vec![];
+
+        for entry in &self.entries {
+            let (kind, name, address, size) = match entry {
+                SymbolEntry::Label { name, address } => (0, name, *address, 0),
+                SymbolEntry::Data { name, address, size } => (1, name, *address, *size),
+            };
+
+            words.extend([kind, address, size, name.encode_utf16().count() as u16]);
+            words.extend(name.encode_utf16());
+        }
+
+        words
+    }
+
+    /// Decode the symbols section of the binary. Returns None if the section is malformed.
+    pub fn from_words(words: &[u16]) -> Option<SymbolMap> {
+        let mut entries = vec![];
+        let mut rest = words;
+
+        while !rest.is_empty() {
+            let [kind, address, size, len] = *rest.get(0..4)? else { return None; };
+            let chars = rest.get(4..(4 + len as usize))?;
+            let name = String::from_utf16(chars).ok()?;
+
+            entries.push(match kind {
+                0 => SymbolEntry::Label { name, address },
+                1 => SymbolEntry::Data { name, address, size },
+                _ => return None,
+            });
+
+            rest = &rest[(4 + len as usize)..];
+        }
+
+        Some(SymbolMap { entries })
+    }
+}
+
+impl fmt::Display for SymbolEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolEntry::Label { name, address } => write!(f, "label {} 0x{:04X}", name, address),
+            SymbolEntry::Data { name, address, size } => write!(f, "data {} 0x{:04X} {}", name, address, size),
+        }
+    }
+}
@@ -4,7 +4,7 @@ use crate::blocks::{Block, BlockData};
 use crate::canvas::{Canvas, CanvasError};
 use crate::blocks::BlockData::{Machine, Memory};
 use crate::canvas::canvas::Errorable;
-use crate::canvas::CanvasError::{BlockNotFound};
+use crate::canvas::CanvasError::{BlockNotFound, MachineError};
 use crate::canvas::{BlockIdInUseSnafu, MachineNotFoundSnafu};
 
 impl Canvas {
@@ -67,7 +67,7 @@ impl Canvas {
     }
 
     pub fn add_machine_with_id(&mut self, id: u16) -> Errorable {
-        self.seq.add(id);
+        self.seq.add(id).map_err(|cause| MachineError { cause })?;
         self.add_block_with_id(id, Machine { machine_id: id })?;
 
         Ok(())
@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use crate::canvas::Canvas;
 use crate::canvas::canvas::Errorable;
 use crate::canvas::CanvasError::MachineError;
-use crate::Event;
+use crate::{Event, ExecutionLimit};
+use crate::SequencerError::ExecutionCycleExceeded;
 
 impl Canvas {
     pub fn tick(&mut self, count: u16) -> Errorable {
@@ -29,12 +30,26 @@ impl Canvas {
     }
 
     /// Run every machine until all halts.
+    /// The machines are stopped by their own execution limits, or by `max_run_ticks` of the sequencer if it is set.
     pub fn run(&mut self) -> Errorable {
         self.seq.ready();
 
-        for _ in 1..1000 {
-            if self.seq.is_halted() { break; }
+        let mut ticks: u32 = 0;
+
+        while !self.seq.is_halted() {
+            if let Some(max_ticks) = self.seq.max_run_ticks {
+                if ticks >= max_ticks {
+                    // A machine that is still running is likely stuck in an infinite loop.
+                    if let Some(id) = self.seq.running_machine() {
+                        return Err(MachineError { cause: ExecutionCycleExceeded { id, limit: ExecutionLimit::RunTicks { ticks: max_ticks } } });
+                    }
+
+                    break;
+                }
+            }
+
             self.tick(1)?;
+            ticks += 1;
         }
 
         self.tick(1)?;
@@ -1,16 +1,41 @@
 use std::fs;
+use std::io::BufWriter;
+use std::sync::{Arc, Mutex};
+use snafu::ensure;
 use crate::binary::bytes::{u16_vec_to_u8, u8_vec_to_u16};
-use crate::{Execute, Machine};
-use crate::cli::CLIError;
-use crate::cli::CLIError::{CannotParse, CannotReadFile, CannotWriteToFile, RunFailed};
-use crate::compile::compile_to_binary;
-use crate::run::load_from_binary;
+use crate::{link, Container, Execute, ExecutionLimits, FsResolver, LinkError, Listing, Machine, ObjectFile, Parser, Profile, SectionKind, SymbolMap, Tracer};
+use crate::Register::PC;
+use crate::cli::{CLIError, Debugger, JsonLinesWriter};
+use crate::cli::cli_error::TruncatedFileSnafu;
+use crate::cli::CLIError::{CannotDisassemble, CannotLink, CannotParse, CannotReadFile, CannotWriteToFile, RunFailed};
+use crate::compile::compile_parser_to_binary;
+use crate::disassemble::disassemble;
+use crate::run::{load_from_binary, read_memory_layout, read_segments};
 
 type Errorable = Result<(), CLIError>;
 
-pub fn compile_to_file(src_path: &str, out_path: &str) -> Errorable {
+pub fn compile_to_file(src_path: &str, out_path: &str, listing_path: Option<&str>, symbols_path: Option<&str>, is_object: bool) -> Errorable {
     let source = fs::read_to_string(&src_path).map_err(|_| CannotReadFile)?;
-    let bytecode = compile_to_binary(&source).map_err(|error| CannotParse { error })?;
+    let parser = parse_source(&source, src_path)?;
+
+    if let Some(listing_path) = listing_path {
+        fs::write(listing_path, Listing::from(&parser).render()).map_err(|_| CannotWriteToFile)?;
+    }
+
+    if let Some(symbols_path) = symbols_path {
+        fs::write(symbols_path, SymbolMap::new(&parser.symbols, &parser.layout).render()).map_err(|_| CannotWriteToFile)?;
+    }
+
+    let bytecode = if is_object {
+        ObjectFile::from(parser).to_words()?
+    } else {
+        // Imported symbols are only resolved when linking the object files.
+        if let Some(name) = parser.imports.first() {
+            return Err(CannotLink { error: LinkError::UndefinedSymbol { name: name.clone() } });
+        }
+
+        compile_parser_to_binary(parser)?
+    };
 
     let bytes = u16_vec_to_u8(bytecode);
     fs::write(out_path, bytes).map_err(|_| CannotWriteToFile)?;
@@ -18,13 +43,33 @@ pub fn compile_to_file(src_path: &str, out_path: &str) -> Errorable {
     Ok(())
 }
 
-pub fn run_from_binary_file(path: &str, is_debug: bool) -> Errorable {
-    let bytes = fs::read(path).map_err(|_| CannotReadFile)?;
+pub fn link_files(paths: &[String], out_path: &str, entry: Option<&str>) -> Errorable {
+    let objects = paths.iter()
+        .map(|path| ObjectFile::from_words(&read_binary_file(path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bytecode = link(&objects, entry).map_err(|error| CannotLink { error })?;
 
-    let mut m = load_from_binary(&u8_vec_to_u16(bytes))?;
+    fs::write(out_path, u16_vec_to_u8(bytecode)).map_err(|_| CannotWriteToFile)?;
+
+    Ok(())
+}
+
+pub fn run_from_binary_file(path: &str, is_debug: bool, is_protected: bool, listing_path: Option<&str>, trace_path: Option<&str>, limits: ExecutionLimits) -> Errorable {
+    let bytes = read_binary_file(path)?;
+
+    // Without a listing file, fall back to the line numbers in the binary.
+    let listing = match listing_path {
+        Some(listing_path) => Some(Listing::parse(&fs::read_to_string(listing_path).map_err(|_| CannotReadFile)?)),
+        None => debug_listing(&bytes)?,
+    };
+
+    let mut m = load_from_binary(&bytes)?;
     m.is_debug = is_debug;
+    m.is_protected = is_protected;
+    m.limits = limits;
 
-    m.run().map_err(|error| RunFailed { error })?;
+    run_with_trace(&mut m, listing.as_ref(), trace_path)?;
 
     if is_debug {
         println!("stack: {:?}", m.mem.read_stack(10));
@@ -33,15 +78,165 @@ pub fn run_from_binary_file(path: &str, is_debug: bool) -> Errorable {
     Ok(())
 }
 
-pub fn run_from_source(path: &str, is_debug: bool) -> Errorable {
+pub fn run_from_source(path: &str, is_debug: bool, is_protected: bool, trace_path: Option<&str>, limits: ExecutionLimits) -> Errorable {
     let source = fs::read_to_string(path).map_err(|_| CannotReadFile)?;
 
-    let m: Result<Machine, _> = (*source).try_into();
-    let mut m = m.map_err(|error| CannotParse { error })?;
+    let parser = parse_source(&source, path)?;
+    let listing = Listing::from(&parser);
+
+    let mut m: Machine = parser.into();
     m.is_debug = is_debug;
+    m.is_protected = is_protected;
+    m.limits = limits;
+
+    run_with_trace(&mut m, Some(&listing), trace_path)?;
 
-    m.run().map_err(|error| RunFailed { error })?;
-   
     Ok(())
 }
 
+/// Run the machine, writing a record of each instruction to the trace file as JSON Lines.
+fn run_with_trace(m: &mut Machine, listing: Option<&Listing>, trace_path: Option<&str>) -> Errorable {
+    let Some(trace_path) = trace_path else {
+        return run_machine(m, listing);
+    };
+
+    let file = fs::File::create(trace_path).map_err(|_| CannotWriteToFile)?;
+    let writer = Arc::new(Mutex::new(JsonLinesWriter::new(BufWriter::new(file))));
+
+    m.tracer = Tracer::new(writer.clone());
+    let result = run_machine(m, listing);
+    m.tracer = Tracer::default();
+
+    // The trace is still written when the program fails, to help find out why.
+    writer.lock().map_err(|_| CannotWriteToFile)?.finish().map_err(|_| CannotWriteToFile)?;
+
+    result
+}
+
+/// Run the machine. In debug mode, print the source line of each instruction before it runs.
+fn run_machine(m: &mut Machine, listing: Option<&Listing>) -> Errorable {
+    let Some(listing) = listing.filter(|_| m.is_debug) else {
+        return m.run().map_err(|error| RunFailed { error });
+    };
+
+    m.reg.set(PC, m.entry);
+
+    while !m.should_halt() {
+        if let Some(entry) = listing.entry_at(m.reg.get(PC)) {
+            println!("{}", entry);
+        }
+
+        m.tick().map_err(|error| RunFailed { error })?;
+    }
+
+    Ok(())
+}
+
+pub fn debug_file(path: &str, from_source: bool, listing_path: Option<&str>) -> Errorable {
+    let (m, listing, symbols) = load_program(path, from_source, listing_path)?;
+
+    Debugger::new(m, listing, symbols).repl();
+
+    Ok(())
+}
+
+/// Run the program, then print how many cycles are spent under each label and the coverage of each source line.
+pub fn profile_file(path: &str, from_source: bool, listing_path: Option<&str>, coverage_path: Option<&str>, limits: ExecutionLimits) -> Errorable {
+    let (mut m, listing, symbols) = load_program(path, from_source, listing_path)?;
+
+    m.profile = Some(Profile::new());
+    m.limits = limits;
+    let result = m.run().map_err(|error| RunFailed { error });
+
+    // The profile is still printed when the program fails, to show where it went.
+    let profile = m.profile.take().unwrap_or_default();
+    print!("{}", profile.render_flat(&symbols));
+
+    let coverage = profile.render_lcov(&listing.unwrap_or_default(), path);
+
+    match coverage_path {
+        Some(coverage_path) => fs::write(coverage_path, coverage).map_err(|_| CannotWriteToFile)?,
+        None => print!("\n{}", coverage),
+    }
+
+    result
+}
+
+/// Load the program from the source or the binary, with its listing and symbols.
+fn load_program(path: &str, from_source: bool, listing_path: Option<&str>) -> Result<(Machine, Option<Listing>, SymbolMap), CLIError> {
+    if from_source {
+        let source = fs::read_to_string(path).map_err(|_| CannotReadFile)?;
+        let parser = parse_source(&source, path)?;
+
+        let listing = Listing::from(&parser);
+        let symbols = SymbolMap::new(&parser.symbols, &parser.layout);
+
+        return Ok((parser.into(), Some(listing), symbols));
+    }
+
+    let bytes = read_binary_file(path)?;
+
+    let listing = match listing_path {
+        Some(listing_path) => Some(Listing::parse(&fs::read_to_string(listing_path).map_err(|_| CannotReadFile)?)),
+        None => debug_listing(&bytes)?,
+    };
+
+    let container = Container::from_words(&bytes)?;
+    let symbols = container.section(SectionKind::Symbols).and_then(SymbolMap::from_words).unwrap_or_default();
+
+    Ok((load_from_binary(&bytes)?, listing, symbols))
+}
+
+pub fn disassemble_file(path: &str, out_path: Option<&str>) -> Errorable {
+    let bytes = read_binary_file(path)?;
+
+    let (code, data) = read_segments(&bytes)?;
+    let layout = read_memory_layout(&bytes)?;
+    let source = disassemble(&code, &data, &layout).map_err(|error| CannotDisassemble { error })?;
+
+    match out_path {
+        Some(out_path) => fs::write(out_path, source).map_err(|_| CannotWriteToFile)?,
+        None => print!("{}", source),
+    }
+
+    Ok(())
+}
+
+/// Read the binary file as words. A file with an odd number of bytes is cut off midway through a word.
+fn read_binary_file(path: &str) -> Result<Vec<u16>, CLIError> {
+    let bytes = fs::read(path).map_err(|_| CannotReadFile)?;
+    ensure!(bytes.len() % 2 == 0, TruncatedFileSnafu);
+
+    Ok(u8_vec_to_u16(bytes))
+}
+
+/// Build the listing from the debug lines section of the binary, if it has one.
+fn debug_listing(bytes: &[u16]) -> Result<Option<Listing>, CLIError> {
+    let container = Container::from_words(bytes)?;
+
+    let (Some(lines), Some(code)) = (container.section(SectionKind::DebugLines), container.section(SectionKind::Code)) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Listing::from_debug_lines(lines, code)))
+}
+
+/// Parse the source file, reading the included files from the filesystem.
+fn parser_of(source: &str, path: &str) -> Parser {
+    Parser::new(source).with_resolver(FsResolver).with_file(path)
+}
+
+/// On errors, print every error and warning in the source code, so they can be fixed in one go.
+fn parse_source(source: &str, path: &str) -> Result<Parser, CLIError> {
+    let mut parser = parser_of(source, path);
+
+    parser.parse().map_err(|error| {
+        let mut parser = parser_of(source, path);
+        let diagnostics = parser.parse_with_recovery();
+        eprint!("{}", diagnostics.render(&parser));
+
+        CannotParse { error }
+    })?;
+
+    Ok(parser)
+}
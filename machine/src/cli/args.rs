@@ -17,6 +17,33 @@ pub enum Commands {
 
         /// Path to the output bytecode.
         out: String,
+
+        /// Write the listing of addresses, encoded words and source lines to the file.
+        #[arg(short, long)]
+        listing: Option<String>,
+
+        /// Write the addresses of the labels and data symbols to the file.
+        #[arg(short, long)]
+        symbols: Option<String>,
+
+        /// Compile into a relocatable object file, to be combined with `link`.
+        #[arg(short = 'c', long)]
+        object: bool,
+    },
+
+    /// Link the object files into one bytecode.
+    Link {
+        /// Paths to the object files, laid out in the given order.
+        #[arg(required = true)]
+        objects: Vec<String>,
+
+        /// Path to the output bytecode.
+        #[arg(short, long)]
+        out: String,
+
+        /// Exported label to start running from. Defaults to the label declared with `.entry`.
+        #[arg(short, long)]
+        entry: Option<String>,
     },
 
     /// Run the bytecode or text assembly format.
@@ -31,5 +58,74 @@ pub enum Commands {
         /// Enable debug mode.
         #[arg(short, long)]
         debug: bool,
+
+        /// Listing file from `compile --listing`, used to show the source lines in debug mode.
+        #[arg(short, long)]
+        listing: Option<String>,
+
+        /// Write a record of each instruction to the file, as JSON Lines.
+        #[arg(short, long)]
+        trace: Option<String>,
+
+        /// Stop with an error after running this many instructions.
+        #[arg(long)]
+        fuel: Option<u64>,
+
+        /// Stop with an error after spending this many cycles.
+        #[arg(long)]
+        max_cycles: Option<u64>,
+
+        /// Fault when the program writes over its code, or touches the stacks by address.
+        #[arg(long)]
+        protect: bool,
+    },
+
+    /// Step through the bytecode or text assembly in an interactive debugger.
+    Debug {
+        /// Path to the bytecode or assembly.
+        path: String,
+
+        /// Debug the text assembly source file instead.
+        #[arg(short, long)]
+        from_source: bool,
+
+        /// Listing file from `compile --listing`, used to show the source lines.
+        #[arg(short, long)]
+        listing: Option<String>,
+    },
+
+    /// Run the bytecode or text assembly, then report the cycles spent under each label and the coverage of each line.
+    Profile {
+        /// Path to the bytecode or assembly.
+        path: String,
+
+        /// Profile the text assembly source file instead.
+        #[arg(short, long)]
+        from_source: bool,
+
+        /// Listing file from `compile --listing`, used to report the coverage of the source lines.
+        #[arg(short, long)]
+        listing: Option<String>,
+
+        /// Write the lcov coverage report to the file. Prints to the console if not specified.
+        #[arg(short, long)]
+        coverage: Option<String>,
+
+        /// Stop with an error after running this many instructions.
+        #[arg(long)]
+        fuel: Option<u64>,
+
+        /// Stop with an error after spending this many cycles.
+        #[arg(long)]
+        max_cycles: Option<u64>,
+    },
+
+    /// Disassemble the bytecode into assembly source.
+    Disasm {
+        /// Path to the bytecode.
+        path: String,
+
+        /// Path to the output assembly. Prints to the console if not specified.
+        out: Option<String>,
     },
 }
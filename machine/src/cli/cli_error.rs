@@ -1,5 +1,5 @@
 use snafu::prelude::*;
-use crate::{ParseError, RuntimeError};
+use crate::{DisassembleError, LinkError, ParseError, RuntimeError};
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub))]
@@ -24,4 +24,37 @@ pub enum CLIError {
 
     #[snafu(display(""))]
     RunFailed { error: RuntimeError },
+
+    #[snafu(display(""))]
+    CannotDisassemble { error: DisassembleError },
+
+    #[snafu(display(""))]
+    TruncatedFile,
+
+    #[snafu(display(""))]
+    UnsupportedVersion { version: u16 },
+
+    #[snafu(display(""))]
+    ChecksumMismatch { kind: u16 },
+
+    #[snafu(display(""))]
+    SectionOutOfRange { kind: u16, offset: usize, size: usize },
+
+    #[snafu(display(""))]
+    MissingSection { kind: u16 },
+
+    #[snafu(display(""))]
+    CorruptSection { kind: u16 },
+
+    #[snafu(display(""))]
+    IncompatibleMemoryLayout,
+
+    #[snafu(display(""))]
+    NotExecutable,
+
+    #[snafu(display(""))]
+    NotObjectFile,
+
+    #[snafu(display(""))]
+    CannotLink { error: LinkError },
 }
@@ -0,0 +1,420 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use crate::{Decode, Execute, Listing, Machine, Op, SymbolEntry, SymbolMap};
+use crate::Register::{BP, FP, PC, SP};
+
+/// Memory address that stops the program when its value changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watch {
+    /// Address or symbol name that the watch is set on.
+    pub name: String,
+
+    pub address: u16,
+
+    /// Value when the watch was last checked.
+    pub value: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugCommand {
+    /// Run one instruction, stepping into calls.
+    Step,
+
+    /// Run one instruction, stepping over calls.
+    Next,
+
+    /// Run until the current function returns.
+    Finish,
+
+    /// Run until a breakpoint, a watch or the end of the program.
+    Continue,
+
+    Break(String),
+    Delete(String),
+    Watch(String),
+    Unwatch(String),
+
+    /// Print the registers.
+    Registers,
+
+    /// Print the data stack.
+    Stack,
+
+    /// Print the return addresses in the call stack segment.
+    CallStack,
+
+    /// Print the words at the address.
+    Memory(String, u16),
+
+    /// Print the instruction at the program counter.
+    Where,
+
+    Help,
+    Quit,
+}
+
+/// Why the program stopped running.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StopReason {
+    Step,
+    Breakpoint(u16),
+    Watch { name: String, old: u16, new: u16 },
+    Halted,
+    Error(String),
+}
+
+/// Step debugger that runs the machine one instruction at a time.
+pub struct Debugger {
+    pub machine: Machine,
+
+    /// Source lines of the instructions, if available.
+    pub listing: Option<Listing>,
+
+    /// Labels and data, used to refer to addresses by name.
+    pub symbols: SymbolMap,
+
+    pub breakpoints: BTreeSet<u16>,
+
+    pub watches: Vec<Watch>,
+}
+
+const HELP: &str = "\
+step, s               run one instruction, stepping into calls
+next, n               run one instruction, stepping over calls
+finish, f             run until the current function returns
+continue, c           run until a breakpoint, a watch or the end
+break, b <addr|label> stop before running the instruction
+delete, d <addr|label> remove the breakpoint
+watch, w <addr|name>  stop when the value at the address changes
+unwatch <addr|name>   remove the watch
+registers, r          print the registers
+stack                 print the data stack
+calls                 print the call stack
+mem, x <addr|name> [count]  print the words in memory
+where                 print the current instruction
+quit, q               stop debugging";
+
+impl Debugger {
+    pub fn new(mut machine: Machine, listing: Option<Listing>, symbols: SymbolMap) -> Debugger {
+        // The debugger prints its own output.
+        machine.is_debug = false;
+        machine.reg.set(PC, machine.entry);
+
+        Debugger { machine, listing, symbols, breakpoints: BTreeSet::new(), watches: vec![] }
+    }
+
+    /// Read the commands from the standard input until the program ends or the user quits.
+    /// An empty line repeats the previous command.
+    pub fn repl(&mut self) {
+        let stdin = io::stdin();
+        let mut last: Option<DebugCommand> = None;
+
+        println!("{}", self.location());
+
+        loop {
+            print!("(debug) ");
+            let _ = io::stdout().flush();
+
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 { break; }
+
+            let command = match (line.trim(), &last) {
+                ("", Some(last)) => Ok(last.clone()),
+                (line, _) => parse_command(line),
+            };
+
+            match command {
+                Ok(DebugCommand::Quit) => break,
+                Ok(command) => {
+                    println!("{}", self.execute(&command));
+                    last = Some(command);
+                }
+                Err(message) => println!("{}", message),
+            }
+        }
+    }
+
+    /// Run the command and return its output.
+    pub fn execute(&mut self, command: &DebugCommand) -> String {
+        use DebugCommand::*;
+
+        let result = match command {
+            Step => {
+                let reason = self.step();
+                Ok(self.describe_stop(reason))
+            }
+
+            Next => {
+                let reason = self.step_over();
+                Ok(self.describe_stop(reason))
+            }
+
+            Finish => self.finish().map(|reason| self.describe_stop(reason)),
+
+            Continue => {
+                let reason = self.resume(|_| false);
+                Ok(self.describe_stop(reason))
+            }
+
+            Break(target) => self.address_of(target).map(|address| {
+                self.breakpoints.insert(address);
+                format!("breakpoint at 0x{:04X}", address)
+            }),
+
+            Delete(target) => self.address_of(target).map(|address| {
+                match self.breakpoints.remove(&address) {
+                    true => format!("deleted breakpoint at 0x{:04X}", address),
+                    false => format!("no breakpoint at 0x{:04X}", address),
+                }
+            }),
+
+            Watch(target) => self.address_of(target).map(|address| {
+                let value = self.machine.mem.get(address);
+                self.watches.push(self::Watch { name: target.clone(), address, value });
+                format!("watching {} at 0x{:04X} = {}", target, address, value)
+            }),
+
+            Unwatch(target) => self.address_of(target).map(|address| {
+                self.watches.retain(|w| w.address != address);
+                format!("stopped watching 0x{:04X}", address)
+            }),
+
+            Registers => Ok(self.registers()),
+            Stack => Ok(format!("stack: {:?}", self.stack())),
+            CallStack => Ok(self.call_stack()),
+            Memory(target, count) => self.address_of(target).map(|address| self.memory(address, *count)),
+            Where => Ok(self.location()),
+            Help => Ok(HELP.into()),
+            Quit => Ok("".into()),
+        };
+
+        result.unwrap_or_else(|message| message)
+    }
+
+    /// Run one instruction, stepping into calls.
+    pub fn step(&mut self) -> StopReason {
+        self.resume(|_| true)
+    }
+
+    /// Run one instruction. Calls are run until they return.
+    pub fn step_over(&mut self) -> StopReason {
+        if !matches!(self.current_op(), Op::Call(..)) {
+            return self.step();
+        }
+
+        let depth = self.machine.reg.get(FP);
+
+        self.resume(|m| m.reg.get(FP) <= depth)
+    }
+
+    /// Run until the current function returns to its caller.
+    pub fn finish(&mut self) -> Result<StopReason, String> {
+        // The call stack is empty when no function is called.
+        let Some(slot) = self.return_slots().pop() else {
+            return Err("not inside a function call".into());
+        };
+
+        // The return address is popped by the `return` of the function, after its stack frame is left.
+        Ok(self.resume(|m| m.reg.get(FP) < slot))
+    }
+
+    /// Run the instructions until `done` is true after an instruction,
+    /// or until a breakpoint, a watch, an error or the end of the program.
+    pub fn resume(&mut self, done: impl Fn(&Machine) -> bool) -> StopReason {
+        loop {
+            if self.machine.should_halt() {
+                return StopReason::Halted;
+            }
+
+            if let Err(error) = self.machine.tick() {
+                return StopReason::Error(error.to_string());
+            }
+
+            for watch in self.watches.iter_mut() {
+                let value = self.machine.mem.get(watch.address);
+
+                if value != watch.value {
+                    let old = std::mem::replace(&mut watch.value, value);
+                    return StopReason::Watch { name: watch.name.clone(), old, new: value };
+                }
+            }
+
+            if done(&self.machine) {
+                return StopReason::Step;
+            }
+
+            let pc = self.machine.reg.get(PC);
+
+            if self.breakpoints.contains(&pc) {
+                return StopReason::Breakpoint(pc);
+            }
+        }
+    }
+
+    /// Values in the data stack, from the bottom to the top.
+    pub fn stack(&self) -> Vec<u16> {
+        self.machine.stack_values()
+    }
+
+    /// Return addresses in the call stack, from the outermost call.
+    pub fn return_addresses(&self) -> Vec<u16> {
+        self.return_slots().iter().map(|slot| self.machine.mem.get(*slot)).collect()
+    }
+
+    /// Addresses in the call stack that hold the return addresses, from the outermost call.
+    ///
+    /// A function that runs `enter` stores the caller's base pointer, the stack pointer and its locals
+    /// after the return address, so the stack frames are found by following the saved base pointers.
+    /// Below the outermost stack frame, every word is a return address.
+    fn return_slots(&self) -> Vec<u16> {
+        let start = self.machine.mem.layout.call_stack_start();
+        let mut end = self.machine.reg.get(FP).wrapping_add(1).max(start);
+        let mut bp = self.machine.reg.get(BP);
+        let mut slots = vec![];
+
+        // The base pointer points at the saved stack pointer, after the caller's base pointer and the return address.
+        while bp >= start + 2 && bp < end {
+            slots.push(bp - 2);
+            end = bp - 2;
+            bp = self.machine.mem.get(bp - 1);
+        }
+
+        slots.extend((start..end).rev());
+        slots.reverse();
+        slots
+    }
+
+    /// Resolve an address, written in decimal or hexadecimal, or as the name of a label or data.
+    pub fn address_of(&self, target: &str) -> Result<u16, String> {
+        let parsed = match target.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => target.parse().ok(),
+        };
+
+        parsed
+            .or_else(|| self.symbols.entries.iter().find(|e| e.name() == target).map(|e| e.address()))
+            .ok_or_else(|| format!("unknown address or symbol '{}'", target))
+    }
+
+    fn describe_stop(&mut self, reason: StopReason) -> String {
+        let message = match reason {
+            StopReason::Step => None,
+            StopReason::Breakpoint(address) => Some(format!("breakpoint at 0x{:04X}", address)),
+            StopReason::Watch { name, old, new } => Some(format!("{} changed from {} to {}", name, old, new)),
+            StopReason::Halted => return format!("program halted\nstack: {:?}", self.stack()),
+            StopReason::Error(error) => return format!("runtime error: {}", error),
+        };
+
+        match message {
+            Some(message) => format!("{}\n{}", message, self.location()),
+            None => self.location(),
+        }
+    }
+
+    /// The instruction at the program counter, with its source line if available.
+    fn location(&mut self) -> String {
+        let pc = self.machine.reg.get(PC);
+
+        let label = self.symbols.entries.iter()
+            .find(|e| matches!(e, SymbolEntry::Label { address, .. } if *address == pc))
+            .map(|e| format!("{}:\n", e.name()))
+            .unwrap_or_default();
+
+        if let Some(entry) = self.listing.as_ref().and_then(|l| l.entry_at(pc)) {
+            return format!("{}{}", label, entry);
+        }
+
+        let op = self.current_op();
+        let args: Vec<String> = op.field_values().iter().map(|v| v.to_string()).collect();
+
+        format!("{}{:04X}  {} {}", label, pc, op, args.join(" ")).trim_end().into()
+    }
+
+    /// Decode the instruction at the program counter without running it.
+    fn current_op(&mut self) -> Op {
+        let pc = self.machine.reg.get(PC);
+        let op = self.machine.decode();
+        self.machine.reg.set(PC, pc);
+
+        op
+    }
+
+    fn registers(&self) -> String {
+        let reg = &self.machine.reg;
+
+        format!("pc 0x{:04X}  sp 0x{:04X}  fp 0x{:04X}  bp 0x{:04X}", reg.get(PC), reg.get(SP), reg.get(FP), reg.get(BP))
+    }
+
+    fn call_stack(&self) -> String {
+        let frames: Vec<String> = self.return_addresses().iter().rev().map(|address| {
+            // The return address points at the operand of the call instruction.
+            let callee = self.machine.mem.get(*address);
+
+            let name = self.symbols.entries.iter()
+                .find(|e| matches!(e, SymbolEntry::Label { address, .. } if *address == callee))
+                .map(|e| e.name().to_owned())
+                .unwrap_or_else(|| format!("0x{:04X}", callee));
+
+            format!("  {} called from 0x{:04X}", name, address.wrapping_sub(1))
+        }).collect();
+
+        match frames.is_empty() {
+            true => "call stack is empty".into(),
+            false => format!("call stack:\n{}", frames.join("\n")),
+        }
+    }
+
+    fn memory(&self, address: u16, count: u16) -> String {
+        let size = self.machine.mem.size() as u16;
+        let count = count.min(size.saturating_sub(address));
+        let words = self.machine.mem.read(address, count);
+
+        words.chunks(8).enumerate().map(|(i, row)| {
+            let row: Vec<String> = row.iter().map(|w| format!("{:04X}", w)).collect();
+            format!("0x{:04X}: {}", address as usize + i * 8, row.join(" "))
+        }).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Parse a line of input into a command.
+pub fn parse_command(line: &str) -> Result<DebugCommand, String> {
+    use DebugCommand::*;
+
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let target = words.next().map(|t| t.to_owned());
+
+    let with_target = |command: fn(String) -> DebugCommand| {
+        target.clone().map(command).ok_or_else(|| format!("'{}' requires an address or a symbol", name))
+    };
+
+    match name {
+        "step" | "s" => Ok(Step),
+        "next" | "n" => Ok(Next),
+        "finish" | "f" => Ok(Finish),
+        "continue" | "c" => Ok(Continue),
+        "break" | "b" => with_target(Break),
+        "delete" | "d" => with_target(Delete),
+        "watch" | "w" => with_target(Watch),
+        "unwatch" => with_target(Unwatch),
+        "registers" | "r" => Ok(Registers),
+        "stack" => Ok(Stack),
+        "calls" => Ok(CallStack),
+        "where" => Ok(Where),
+        "help" | "h" => Ok(Help),
+        "quit" | "q" => Ok(Quit),
+
+        "mem" | "x" => {
+            let target = target.ok_or_else(|| format!("'{}' requires an address or a symbol", name))?;
+
+            let count = match words.next() {
+                Some(count) => count.parse().map_err(|_| format!("invalid count '{}'", count))?,
+                None => 1,
+            };
+
+            Ok(Memory(target, count))
+        }
+
+        _ => Err(format!("unknown command '{}'. type 'help' for the list of commands", name)),
+    }
+}
@@ -1,7 +1,11 @@
 pub mod args;
 pub mod actions;
 pub mod cli_error;
+pub mod debugger;
+pub mod trace_writer;
 
 pub use args::*;
 pub use actions::*;
 pub use cli_error::CLIError;
+pub use debugger::*;
+pub use trace_writer::JsonLinesWriter;
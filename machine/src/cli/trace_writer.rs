@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use crate::{TraceRecord, TraceSink};
+
+/// Writes each trace record as a line of JSON.
+///
+/// ```text
+/// {"pc":0,"op":"push 10","stack_before":[],"stack_after":[10],"writes":[],"messages":[]}
+/// ```
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+
+    /// First error from writing the records. Later records are dropped.
+    pub error: Option<io::Error>,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> JsonLinesWriter<W> {
+        JsonLinesWriter { writer, error: None }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Flush the records, returning the first error from writing them.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+impl<W: Write + Send> TraceSink for JsonLinesWriter<W> {
+    fn record(&mut self, record: TraceRecord) {
+        if self.error.is_some() { return; }
+
+        let result = serde_json::to_string(&record)
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(self.writer, "{}", line));
+
+        if let Err(error) = result {
+            self.error = Some(error);
+        }
+    }
+}
@@ -21,3 +21,14 @@ pub use binary::*;
 pub use sequencer::*;
 pub use test_helper::*;
 pub use canvas::*;
+
+// The machines and the canvas can be moved to another thread, such as a worker that runs them.
+const _: () = {
+    fn assert_send<T: Send + std::panic::UnwindSafe>() {}
+
+    #[allow(dead_code)]
+    fn assert_machine_and_canvas() {
+        assert_send::<Machine>();
+        assert_send::<Canvas>();
+    }
+};
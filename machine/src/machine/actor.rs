@@ -1,4 +1,4 @@
-use crate::{Action, Machine, MEMORY_SIZE, Message, RuntimeError};
+use crate::{Action, Machine, Message, RuntimeError};
 use crate::canvas::wire::port;
 
 type Errorable = Result<(), RuntimeError>;
@@ -45,7 +45,7 @@ impl Actor for Machine {
                 Action::Write { address, data } => {
                     // Check if the data is within the bounds of the memory.
                     let last_address = address as usize + data.len();
-                    if last_address >= MEMORY_SIZE as usize { continue; }
+                    if last_address >= self.mem.size() { continue; }
 
                     for (i, byte) in data.iter().enumerate() {
                         self.mem.set(address + i as u16, *byte);
@@ -1,13 +1,13 @@
 use std::ops::Not;
 use snafu::ensure;
-use crate::{Event, RuntimeError};
+use crate::{Event, MemorySegment, RuntimeError, TraceRecord};
 use crate::machine::{Decode, Machine};
-use crate::register::Register::PC;
+use crate::register::Register::{BP, FP, PC, SP};
 use crate::op::Op;
 use crate::mem::WithStringManager;
 use crate::machine::{Action, Actor};
 use crate::machine::virtual_mem::VirtualMemory;
-use crate::runtime_error::{IndexOutOfBoundsSnafu, NotEnoughValuesSnafu};
+use crate::runtime_error::{CannotDivideByZeroSnafu, CycleLimitExceededSnafu, ExecuteFaultSnafu, FuelExhaustedSnafu, IndexOutOfBoundsSnafu, MissingStackFrameSnafu, NegativeSquareRootSnafu, NotEnoughValuesSnafu, ReadFaultSnafu, WriteFaultSnafu};
 use crate::RuntimeError::{CallStackExceeded, CannotDivideByZero, CannotLoadFromMemory, IntegerOverflow, IntegerUnderflow, MissingMessageBody, MissingReturnAddress, MissingValueToStore};
 
 type Errorable = Result<(), RuntimeError>;
@@ -27,8 +27,178 @@ pub trait Execute {
 }
 
 impl Execute for Machine {
-    /// Execute an instruction.
+    /// Execute an instruction, recording it if tracing is enabled.
     fn exec_op(&mut self, op: Op) -> Errorable {
+        if !self.tracer.is_enabled() {
+            return self.exec_instruction(op);
+        }
+
+        let pc = self.instruction_address(op);
+        let stack_before = self.stack_values();
+        let sent = self.outbox.len();
+
+        self.mem.start_journal();
+        let result = self.exec_instruction(op);
+
+        // Writes to the data stack are already shown in the stack.
+        let stack = self.mem.layout.stack_start()..=self.mem.layout.stack_end();
+        let writes = self.mem.take_journal().into_iter()
+            .filter(|w| !stack.contains(&w.address))
+            .collect();
+
+        self.tracer.record(TraceRecord {
+            pc,
+            op: TraceRecord::describe(&op),
+            stack_before,
+            stack_after: self.stack_values(),
+            writes,
+            messages: self.outbox.get(sent..).unwrap_or_default().to_vec(),
+        });
+
+        result
+    }
+
+    // Fetch, decode and execute the instruction.
+    fn tick(&mut self) -> Errorable {
+        let pc = self.reg.get(PC);
+
+        if self.is_protected {
+            let segment = self.mem.layout.segment_of(pc);
+            ensure!(segment == Some(MemorySegment::Code), ExecuteFaultSnafu { pc });
+        }
+
+        let op = self.decode();
+
+        // Stay on the instruction, so the machine can resume once the limits are raised.
+        if let Err(error) = self.spend(op.cycles()) {
+            self.reg.set(PC, pc);
+            return Err(error);
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc, op.cycles());
+        }
+
+        self.exec_op(op)
+    }
+
+    fn run(&mut self) -> Errorable {
+        self.reg.set(PC, self.entry);
+
+        while !self.should_halt() {
+            self.tick()?;
+        }
+
+        Ok(())
+    }
+
+    fn should_halt(&self) -> bool {
+        let op: Op = self.opcode().into();
+
+        op == Op::Halt || op == Op::Eof
+    }
+}
+
+impl Machine {
+    /// Count the instruction and its cycles against the execution limits.
+    fn spend(&mut self, cycles: u64) -> Errorable {
+        if let Some(fuel) = self.limits.fuel {
+            ensure!(self.instructions < fuel, FuelExhaustedSnafu { fuel });
+        }
+
+        if let Some(max_cycles) = self.limits.max_cycles {
+            ensure!(self.cycles + cycles <= max_cycles, CycleLimitExceededSnafu { max_cycles });
+        }
+
+        self.instructions += 1;
+        self.cycles += cycles;
+
+        Ok(())
+    }
+
+    /// Address of the instruction. The program counter is at the last operand once the instruction is decoded.
+    fn instruction_address(&self, op: Op) -> u16 {
+        self.reg.get(PC).wrapping_sub(op.arity() as u16)
+    }
+
+    /// Memory that the instruction reads or writes by its address: whether it writes, the address, and the number of words.
+    /// Instructions that take the address from the stack are skipped when the stack is empty, so they fail as usual.
+    /// Strings are checked by their first word, as their length is only known once they are read.
+    fn memory_access(&self, op: Op) -> Vec<(bool, u16, u16)> {
+        let sp = self.reg.get(SP);
+        let stack_start = self.mem.layout.stack_start();
+        let top = (sp >= stack_start).then(|| self.mem.get(sp));
+        let second = (sp > stack_start).then(|| self.mem.get(sp - 1));
+
+        let access = match op {
+            Op::Load(address) | Op::LoadString(address) => Some((false, address, 1)),
+            Op::Load32(address) => Some((false, address, 2)),
+            Op::Store(address) => Some((true, address, 1)),
+            Op::Store32(address) => Some((true, address, 2)),
+            Op::Read(size) => top.map(|address| (false, address, size)),
+            Op::Write(size) => top.map(|address| (true, address, size)),
+            Op::LoadI | Op::LoadIInc | Op::StrLen => top.map(|address| (false, address, 1)),
+            Op::StoreI | Op::StoreIInc => top.map(|address| (true, address, 1)),
+            Op::StrCmp => return second.into_iter().chain(top).map(|address| (false, address, 1)).collect(),
+            _ => None,
+        };
+
+        access.into_iter().collect()
+    }
+
+    /// Fault when the instruction touches memory that is off-limits to it.
+    /// The code can only be read, and the stacks can only be touched by the stack operations.
+    fn check_access(&self, op: Op) -> Errorable {
+        let pc = self.instruction_address(op);
+
+        for (is_write, start, count) in self.memory_access(op) {
+            for offset in 0..count {
+                let address = start.wrapping_add(offset);
+                let segment = self.mem.layout.segment_of(address);
+
+                if is_write {
+                    ensure!(segment.is_some_and(|s| s.is_writable()), WriteFaultSnafu { pc, address });
+                } else {
+                    ensure!(segment.is_some_and(|s| s.is_readable()), ReadFaultSnafu { pc, address });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the base pointer of the stack frame.
+    fn frame(&self) -> Result<u16, RuntimeError> {
+        let bp = self.reg.get(BP);
+        ensure!(bp >= self.mem.layout.call_stack_start(), MissingStackFrameSnafu);
+
+        Ok(bp)
+    }
+
+    /// Returns the address of the nth local, which comes after the base pointer.
+    fn local_address(&self, n: u16) -> Result<u16, RuntimeError> {
+        let bp = self.frame()?;
+        let len = self.reg.get(FP).saturating_sub(bp);
+        ensure!(n < len, IndexOutOfBoundsSnafu { index: n, len });
+
+        Ok(bp + 1 + n)
+    }
+
+    /// Returns the address of the nth argument on the data stack, counting down from the stack pointer at entry.
+    fn arg_address(&self, n: u16) -> Result<u16, RuntimeError> {
+        let top = self.mem.get(self.frame()?);
+        let len = top.wrapping_add(1).saturating_sub(self.mem.layout.stack_start());
+        ensure!(n < len, IndexOutOfBoundsSnafu { index: n, len });
+
+        Ok(top - n)
+    }
+
+    /// Execute an instruction.
+    fn exec_instruction(&mut self, op: Op) -> Errorable {
+        if self.is_protected {
+            self.check_access(op)?;
+        }
+
         // Should we jump to a different instruction?
         let mut jump: Option<u16> = None;
 
@@ -88,6 +258,69 @@ impl Execute for Machine {
                 }
             }
 
+            // Indirect addressing, with the address popped from the stack.
+            Op::LoadI | Op::LoadIInc => {
+                let address = s.pop()?;
+
+                if op == Op::LoadIInc {
+                    s.push(address.wrapping_add(1))?;
+                }
+
+                if !self.read_virtual(address, 1) {
+                    let v = self.mem.get(address);
+                    self.stack().push(v).map_err(|_| CannotLoadFromMemory)?;
+                }
+            }
+
+            Op::StoreI | Op::StoreIInc => {
+                let address = s.pop()?;
+                let value = s.pop().map_err(|_| MissingValueToStore)?;
+
+                if op == Op::StoreIInc {
+                    s.push(address.wrapping_add(1))?;
+                }
+
+                if !self.write_virtual(address, vec![value]) {
+                    self.mem.set(address, value);
+                }
+            }
+
+            Op::IncBy(n) => s.apply(|a| a.checked_add(n).ok_or(IntegerOverflow))?,
+
+            // Locals, relative to the frame pointer.
+            Op::LoadLocal(n) => {
+                let address = self.local_address(n)?;
+                let v = self.mem.get(address);
+
+                self.stack().push(v)?;
+            }
+
+            Op::StoreLocal(n) => {
+                let value = s.pop().map_err(|_| MissingValueToStore)?;
+                let address = self.local_address(n)?;
+
+                self.mem.set(address, value);
+            }
+
+            // Dynamic memory on the heap.
+            Op::Alloc(size) => {
+                let address = self.heap().alloc(size)?;
+                self.stack().push(address)?;
+            }
+
+            Op::Free => {
+                let address = s.pop()?;
+                self.heap().free(address)?;
+            }
+
+            Op::Realloc => {
+                let size = s.pop()?;
+                let address = s.pop()?;
+
+                let address = self.heap().realloc(address, size)?;
+                self.stack().push(address)?;
+            }
+
             // Addition, subtraction, multiplication, division and modulo.
             Op::Add => s.apply_two(|a, b| a.checked_add(b).ok_or(IntegerOverflow))?,
             Op::Sub => s.apply_two(|a, b| a.checked_sub(b).ok_or(IntegerUnderflow))?,
@@ -95,6 +328,107 @@ impl Execute for Machine {
             Op::Div => s.apply_two(|a, b| a.checked_div(b).ok_or(CannotDivideByZero))?,
             Op::Mod => s.apply_two(|a, b| Ok(a % b))?,
 
+            // Signed arithmetic on two's complement integers.
+            Op::AddS => s.apply_two_signed(|a, b| a.checked_add(b).ok_or_else(|| overflow(b > 0)))?,
+            Op::SubS => s.apply_two_signed(|a, b| a.checked_sub(b).ok_or_else(|| overflow(b < 0)))?,
+            Op::MulS => s.apply_two_signed(|a, b| a.checked_mul(b).ok_or_else(|| overflow((a < 0) == (b < 0))))?,
+
+            Op::DivS => s.apply_two_signed(|a, b| {
+                ensure!(b != 0, CannotDivideByZeroSnafu);
+
+                // Dividing -32768 by -1 is the only overflow.
+                a.checked_div(b).ok_or(IntegerOverflow)
+            })?,
+
+            Op::ModS => s.apply_two_signed(|a, b| {
+                ensure!(b != 0, CannotDivideByZeroSnafu);
+
+                Ok(a.wrapping_rem(b))
+            })?,
+
+            Op::Neg => s.apply_signed(|v| v.checked_neg().ok_or(IntegerOverflow))?,
+
+            // Wrapping arithmetic.
+            Op::AddWrap => s.apply_two(|a, b| Ok(a.wrapping_add(b)))?,
+            Op::SubWrap => s.apply_two(|a, b| Ok(a.wrapping_sub(b)))?,
+            Op::MulWrap => s.apply_two(|a, b| Ok(a.wrapping_mul(b)))?,
+
+            // Saturating arithmetic.
+            Op::AddSat => s.apply_two(|a, b| Ok(a.saturating_add(b)))?,
+            Op::SubSat => s.apply_two(|a, b| Ok(a.saturating_sub(b)))?,
+            Op::MulSat => s.apply_two(|a, b| Ok(a.saturating_mul(b)))?,
+            Op::AddSatS => s.apply_two_signed(|a, b| Ok(a.saturating_add(b)))?,
+            Op::SubSatS => s.apply_two_signed(|a, b| Ok(a.saturating_sub(b)))?,
+            Op::MulSatS => s.apply_two_signed(|a, b| Ok(a.saturating_mul(b)))?,
+
+            // Double-word operations on 32-bit values, which take up two words on the stack.
+            Op::Push32(v) => s.push32(v)?,
+
+            Op::Load32(addr) => {
+                if !self.read_virtual(addr, 2) {
+                    let [high, low] = [self.mem.get(addr), self.mem.get(addr.wrapping_add(1))];
+                    let mut s = self.stack();
+                    s.push(high).map_err(|_| CannotLoadFromMemory)?;
+                    s.push(low).map_err(|_| CannotLoadFromMemory)?;
+                }
+            }
+
+            Op::Store32(addr) => {
+                let value = s.pop32().map_err(|_| MissingValueToStore)?;
+                let words = vec![(value >> 16) as u16, value as u16];
+
+                if !self.write_virtual(addr, words.clone()) {
+                    self.mem.write(addr, &words);
+                }
+            }
+
+            Op::Add32 => s.apply_two32(|a, b| a.checked_add(b).ok_or(IntegerOverflow))?,
+            Op::Sub32 => s.apply_two32(|a, b| a.checked_sub(b).ok_or(IntegerUnderflow))?,
+            Op::Mul32 => s.apply_two32(|a, b| a.checked_mul(b).ok_or(IntegerOverflow))?,
+
+            Op::DivMod32 => {
+                let b = s.pop32()?;
+                let a = s.pop32()?;
+                ensure!(b != 0, CannotDivideByZeroSnafu);
+
+                s.push32(a / b)?;
+                s.push32(a % b)?;
+            }
+
+            // Fixed-point operations on signed Q8.8 values.
+            Op::FMul => s.apply_two_signed(|a, b| to_fixed(div_round(a as i32 * b as i32, 256)))?,
+
+            Op::FDiv => s.apply_two_signed(|a, b| {
+                ensure!(b != 0, CannotDivideByZeroSnafu);
+
+                to_fixed(div_round((a as i32) << 8, b as i32))
+            })?,
+
+            Op::FSin => s.apply_signed(|a| Ok((from_fixed(a).sin() * 256.0).round() as i16))?,
+
+            Op::FSqrt => s.apply_signed(|a| {
+                ensure!(a >= 0, NegativeSquareRootSnafu);
+
+                Ok((from_fixed(a).sqrt() * 256.0).round() as i16)
+            })?,
+
+            Op::Lerp => {
+                let t = s.pop()? as i16 as i32;
+
+                s.apply_two_signed(|a, b| {
+                    let (a, b) = (a as i32, b as i32);
+
+                    to_fixed(a + div_round((b - a) * t, 256))
+                })?
+            }
+
+            Op::Cmp32 => {
+                let b = s.pop32()?;
+                let a = s.pop32()?;
+
+                s.push(a.cmp(&b) as i16 as u16)?;
+            }
+
             // Increment and decrement.
             Op::Inc => s.apply(|v| v.checked_add(1).ok_or(IntegerOverflow))?,
             Op::Dec => s.apply(|v| Ok(v.checked_sub(1).unwrap_or(0)))?,
@@ -107,6 +441,12 @@ impl Execute for Machine {
             Op::GreaterThan => s.apply_two(|a, b| Ok((a > b).into()))?,
             Op::GreaterThanOrEqual => s.apply_two(|a, b| Ok((a >= b).into()))?,
 
+            // Signed comparison operations.
+            Op::LessThanS => s.apply_two_signed(|a, b| Ok((a < b).into()))?,
+            Op::LessThanOrEqualS => s.apply_two_signed(|a, b| Ok((a <= b).into()))?,
+            Op::GreaterThanS => s.apply_two_signed(|a, b| Ok((a > b).into()))?,
+            Op::GreaterThanOrEqualS => s.apply_two_signed(|a, b| Ok((a >= b).into()))?,
+
             // TODO: write a unit test for jump, and op that uses jump.
             //       there was a bug caused by using set(PC) instead of assigning to jump
             Op::Jump(addr) => {
@@ -209,6 +549,32 @@ impl Execute for Machine {
                 }
             }
 
+            Op::StrLen => {
+                let address = s.pop()?;
+                let len = self.mem.string().read_str(address).len() as u16;
+
+                self.stack().push(len)?;
+            }
+
+            Op::StrCmp => {
+                let b = s.pop()?;
+                let a = s.pop()?;
+
+                let strings = self.mem.string();
+                let ordering = strings.read_str(a).cmp(&strings.read_str(b));
+
+                self.stack().push(ordering as i16 as u16)?;
+            }
+
+            Op::Itoa | Op::ItoaS => {
+                let v = s.pop()?;
+                let text = if op == Op::ItoaS { (v as i16).to_string() } else { v.to_string() };
+
+                for c in text.encode_utf16() {
+                    self.stack().push(c)?;
+                }
+            }
+
             Op::Call(address) => {
                 let pc = self.reg.get(PC);
                 self.call_stack().push(pc).map_err(|_| CallStackExceeded)?;
@@ -221,6 +587,45 @@ impl Execute for Machine {
                 jump = Some(address + 1)
             }
 
+            Op::Enter(n) => {
+                let (bp, sp) = (self.reg.get(BP), self.reg.get(SP));
+
+                let mut c = self.call_stack();
+                c.push(bp).map_err(|_| CallStackExceeded)?;
+                c.push(sp).map_err(|_| CallStackExceeded)?;
+                let base = c.top();
+
+                for _ in 0..n {
+                    c.push(0).map_err(|_| CallStackExceeded)?;
+                }
+
+                self.reg.set(BP, base);
+            }
+
+            Op::Leave => {
+                let base = self.frame()?;
+                self.reg.set(FP, base);
+
+                // Drop the saved SP, then restore the caller's frame.
+                let mut c = self.call_stack();
+                c.pop()?;
+                let bp = c.pop()?;
+
+                self.reg.set(BP, bp);
+            }
+
+            Op::LoadArg(n) => {
+                let v = self.mem.get(self.arg_address(n)?);
+                self.stack().push(v)?;
+            }
+
+            Op::StoreArg(n) => {
+                let value = s.pop().map_err(|_| MissingValueToStore)?;
+                let address = self.arg_address(n)?;
+
+                self.mem.set(address, value);
+            }
+
             Op::Send(port, size) => {
                 let mut body = vec![];
 
@@ -244,6 +649,9 @@ impl Execute for Machine {
             Op::LeftShift => s.apply_two(|a, b| Ok(a << b))?,
             Op::RightShift => s.apply_two(|a, b| Ok(a >> b))?,
 
+            // Shifting by 15 or more fills every bit with the sign bit.
+            Op::ArithmeticRightShift => s.apply_two(|a, b| Ok(((a as i16) >> b.min(15)) as u16))?,
+
             // Pause the execution of the thread
             Op::SleepTick(tick) => {
                 self.sleeping = true;
@@ -266,26 +674,38 @@ impl Execute for Machine {
         Ok(())
     }
 
-    // Fetch, decode and execute the instruction.
-    fn tick(&mut self) -> Errorable {
-        let op = self.decode();
+    /// Values in the data stack, from the bottom to the top.
+    pub fn stack_values(&self) -> Vec<u16> {
+        let start = self.mem.layout.stack_start();
+        let count = (self.reg.get(SP) + 1).saturating_sub(start);
 
-        self.exec_op(op)
+        self.mem.read(start, count)
     }
+}
 
-    fn run(&mut self) -> Errorable {
-        self.reg.set(PC, 0);
+/// Signed overflow in the positive direction, or underflow in the negative direction.
+fn overflow(is_positive: bool) -> RuntimeError {
+    if is_positive { IntegerOverflow } else { IntegerUnderflow }
+}
 
-        while !self.should_halt() {
-            self.tick()?;
-        }
+/// Fit the result of a fixed-point operation back into a Q8.8 value.
+fn to_fixed(value: i32) -> Result<i16, RuntimeError> {
+    i16::try_from(value).map_err(|_| overflow(value > 0))
+}
 
-        Ok(())
-    }
+fn from_fixed(value: i16) -> f64 {
+    value as f64 / 256.0
+}
 
-    fn should_halt(&self) -> bool {
-        let op: Op = self.opcode().into();
+/// Divide and round to the nearest integer, with halves away from zero.
+/// Every fixed-point instruction rounds the same way as `f64::round`.
+fn div_round(dividend: i32, divisor: i32) -> i32 {
+    let quotient = dividend / divisor;
+    let remainder = dividend % divisor;
 
-        op == Op::Halt || op == Op::Eof
+    if remainder.abs() * 2 >= divisor.abs() {
+        quotient + dividend.signum() * divisor.signum()
+    } else {
+        quotient
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+/// Limits on how much a machine can run, so that a runaway program stops with an error instead of looping forever.
+///
+/// The limits count the instructions and cycles run since the machine is reset, not the time elapsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionLimits {
+    /// Maximum number of instructions to run.
+    pub fuel: Option<u64>,
+
+    /// Maximum number of cycles to run, as counted by `Op::cycles`.
+    pub max_cycles: Option<u64>,
+}
+
+impl ExecutionLimits {
+    pub fn new(fuel: Option<u64>, max_cycles: Option<u64>) -> ExecutionLimits {
+        ExecutionLimits { fuel, max_cycles }
+    }
+
+    /// No limits. The machine can run forever.
+    pub fn unlimited() -> ExecutionLimits {
+        ExecutionLimits::default()
+    }
+}
+
+/// The limit that a machine runs out of, which stops it with an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum ExecutionLimit {
+    /// The machine runs out of instructions, set by `ExecutionLimits::fuel`.
+    Fuel { fuel: u64 },
+
+    /// The machine runs out of cycles, set by `ExecutionLimits::max_cycles`.
+    Cycles { max_cycles: u64 },
+
+    /// The machine is still running when the canvas runs out of ticks, set by `Sequencer::max_run_ticks`.
+    RunTicks { ticks: u32 },
+}
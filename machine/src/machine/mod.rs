@@ -1,13 +1,16 @@
 pub mod actor;
 pub mod decode;
 pub mod execute;
+pub mod limits;
+pub mod profile;
 pub mod runtime_error;
+pub mod trace;
 mod virtual_mem;
 
 use std::collections::VecDeque;
 use serde::{Deserialize, Serialize};
-use crate::mem::{Memory, StackManager};
-use crate::{CALL_STACK_END, CALL_STACK_START, Op, ParseError, Parser, Register::FP, Registers};
+use crate::mem::{HeapManager, Memory, StackManager};
+use crate::{LayoutError, MemoryLayout, Op, ParseError, Parser, Register::{FP, PC}, Registers};
 
 pub use self::actor::Actor;
 pub use self::decode::Decode;
@@ -15,6 +18,9 @@ pub use crate::canvas::event::Event;
 pub use self::execute::Execute;
 pub use crate::canvas::message::{Action, Message};
 pub use self::runtime_error::RuntimeError;
+pub use self::limits::{ExecutionLimit, ExecutionLimits};
+pub use self::profile::{LabelProfile, Profile};
+pub use self::trace::{MemoryWrite, RingBuffer, TraceRecord, TraceSink, Tracer};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Machine {
@@ -36,9 +42,29 @@ pub struct Machine {
     /// Outbox contains messages sent from this machine.
     pub outbox: Vec<Message>,
 
+    /// Address of the first instruction to run.
+    #[serde(default)]
+    pub entry: u16,
+
+    /// Stops the machine with an error once it runs too many instructions or cycles.
+    #[serde(default)]
+    pub limits: ExecutionLimits,
+
+    /// Instructions run since the machine is reset.
+    #[serde(default)]
+    pub instructions: u64,
+
+    /// Cycles spent since the machine is reset.
+    #[serde(default)]
+    pub cycles: u64,
+
     /// Is the machine in debug mode?
     pub is_debug: bool,
 
+    /// Should the machine fault when the program touches memory it should not, such as by writing over its code?
+    #[serde(default)]
+    pub is_protected: bool,
+
     /// How many messages does the machine expect to receive?
     pub expected_receives: u16,
 
@@ -47,26 +73,57 @@ pub struct Machine {
 
     /// How many tick remains until we resume execution?
     pub remaining_sleep_ticks: u16,
+
+    /// Receives a record of each instruction, if tracing is enabled.
+    #[serde(skip)]
+    pub tracer: Tracer,
+
+    /// Counts the runs and cycles of each instruction, if profiling is enabled.
+    #[serde(skip)]
+    pub profile: Option<Profile>,
 }
 
 impl Machine {
     /// Creates a new machine.
     pub fn new() -> Machine {
+        Machine::laid_out(MemoryLayout::default())
+    }
+
+    /// Creates a new machine, with the segments laid out in its memory.
+    pub fn with_layout(layout: MemoryLayout) -> Result<Machine, LayoutError> {
+        layout.validate()?;
+
+        Ok(Machine::laid_out(layout))
+    }
+
+    /// Creates a new machine with the layout, which is already validated.
+    fn laid_out(layout: MemoryLayout) -> Machine {
+        let mut reg = Registers::new();
+        reg.reset(&layout);
+
         Machine {
             id: None,
 
-            mem: Memory::new(),
-            reg: Registers::new(),
+            mem: Memory::with_layout(layout),
+            reg,
 
             events: vec![],
             inbox: VecDeque::new(),
             outbox: vec![],
 
+            entry: layout.code_start(),
+            limits: ExecutionLimits::unlimited(),
+            instructions: 0,
+            cycles: 0,
             is_debug: false,
+            is_protected: false,
             expected_receives: 0,
 
             sleeping: false,
             remaining_sleep_ticks: 0,
+
+            tracer: Tracer::default(),
+            profile: None,
         }
     }
 
@@ -80,11 +137,16 @@ impl Machine {
     pub fn call_stack(&mut self) -> StackManager {
         let mut stack = self.stack();
         stack.sp = FP;
-        stack.min = CALL_STACK_START;
-        stack.max = CALL_STACK_END;
+        stack.min = stack.mem.layout.call_stack_start();
+        stack.max = stack.mem.layout.call_stack_end();
         stack
     }
 
+    /// Returns a heap manager for the current machine.
+    pub fn heap(&mut self) -> HeapManager<'_> {
+        HeapManager::new(&mut self.mem)
+    }
+
     /// Reset the machine completely.
     pub fn full_reset(&mut self) {
         self.partial_reset();
@@ -96,11 +158,15 @@ impl Machine {
 
     /// Reset the execution state and execution memory of the machine only.
     pub fn partial_reset(&mut self) {
-        self.reg.reset();
+        self.reg.reset(&self.mem.layout);
+        self.reg.set(PC, self.entry);
         self.mem.reset_stacks();
+        self.mem.reset_heap();
         self.expected_receives = 0;
         self.sleeping = false;
         self.remaining_sleep_ticks = 0;
+        self.instructions = 0;
+        self.cycles = 0;
     }
 }
 
@@ -112,14 +178,26 @@ impl From<Vec<Op>> for Machine {
     }
 }
 
+impl From<Parser> for Machine {
+    fn from(parser: Parser) -> Self {
+        let entry = parser.entry_address();
+
+        // The parser only takes valid layouts.
+        let mut machine = Machine::laid_out(parser.layout);
+        machine.mem.load_code(parser.ops);
+        machine.mem.load_symbols(parser.symbols);
+        machine.entry = entry;
+        machine.reg.set(PC, entry);
+        machine
+    }
+}
+
 impl TryFrom<&str> for Machine {
     type Error = ParseError;
 
     fn try_from(source: &str) -> Result<Self, Self::Error> {
         let parser: Parser = source.try_into()?;
 
-        let mut machine: Self = parser.ops.into();
-        machine.mem.load_symbols(parser.symbols);
-        Ok(machine)
+        Ok(parser.into())
     }
 }
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use crate::{Listing, SymbolEntry, SymbolMap};
+
+/// Name of the instructions that come before the first label.
+const NO_LABEL: &str = "(no label)";
+
+/// How many times each instruction has run, and the cycles spent on it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// Execution count of each instruction, by its address.
+    pub counts: BTreeMap<u16, u64>,
+
+    /// Cycles spent on each instruction, by its address.
+    pub cycles: BTreeMap<u16, u64>,
+}
+
+/// Execution count and cycles of the instructions under a label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelProfile {
+    pub name: String,
+    pub address: u16,
+    pub count: u64,
+    pub cycles: u64,
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        Profile::default()
+    }
+
+    /// Record a run of the instruction at the address.
+    pub fn record(&mut self, address: u16, cycles: u64) {
+        *self.counts.entry(address).or_default() += 1;
+        *self.cycles.entry(address).or_default() += cycles;
+    }
+
+    pub fn count_at(&self, address: u16) -> u64 {
+        self.counts.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn total_cycles(&self) -> u64 {
+        self.cycles.values().sum()
+    }
+
+    /// Sum up the instructions under each label, up to the next label.
+    /// The labels are sorted by their cycles, from the most to the least.
+    pub fn by_label(&self, symbols: &SymbolMap) -> Vec<LabelProfile> {
+        let mut labels: Vec<(u16, &str)> = symbols.entries.iter().filter_map(|entry| match entry {
+            SymbolEntry::Label { name, address } => Some((*address, name.as_str())),
+            _ => None,
+        }).collect();
+
+        labels.sort();
+
+        let mut profiles: Vec<LabelProfile> = vec![];
+
+        for (address, cycles) in &self.cycles {
+            // Find the closest label at or before the instruction.
+            let (start, name) = match labels.iter().rev().find(|(start, _)| start <= address) {
+                Some((start, name)) => (*start, *name),
+                None => (0, NO_LABEL),
+            };
+
+            let index = match profiles.iter().position(|p| p.name == name) {
+                Some(index) => index,
+                None => {
+                    profiles.push(LabelProfile { name: name.into(), address: start, count: 0, cycles: 0 });
+                    profiles.len() - 1
+                }
+            };
+
+            profiles[index].count += self.count_at(*address);
+            profiles[index].cycles += cycles;
+        }
+
+        profiles.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.address.cmp(&b.address)));
+        profiles
+    }
+
+    /// Render the flat profile of the labels.
+    ///
+    /// ```text
+    ///  % cycles    cycles     count  label
+    ///    75.00%        30        20  loop
+    ///    25.00%        10         6  start
+    /// ```
+    pub fn render_flat(&self, symbols: &SymbolMap) -> String {
+        let total = self.total_cycles();
+        let mut text = format!("{:>9} {:>9} {:>9}  {}\n", "% cycles", "cycles", "count", "label");
+
+        for p in self.by_label(symbols) {
+            let percent = if total == 0 { 0.0 } else { p.cycles as f64 * 100.0 / total as f64 };
+            text += &format!("{:>8.2}% {:>9} {:>9}  {}\n", percent, p.cycles, p.count, p.name);
+        }
+
+        text
+    }
+
+    /// Render the coverage of each source line in the lcov format.
+    /// Lines in the main source code are reported under `main_file`.
+    pub fn render_lcov(&self, listing: &Listing, main_file: &str) -> String {
+        // Execution count of each line, by file.
+        let mut files: BTreeMap<&str, BTreeMap<usize, u64>> = BTreeMap::new();
+
+        for entry in &listing.entries {
+            let file = entry.file.as_deref().unwrap_or(main_file);
+            let count = files.entry(file).or_default().entry(entry.line).or_default();
+
+            // Instructions expanded from the same line run together, such as in macros.
+            *count = (*count).max(self.count_at(entry.address));
+        }
+
+        let mut text = String::new();
+
+        for (file, lines) in files {
+            text += &format!("TN:\nSF:{}\n", file);
+
+            for (line, count) in &lines {
+                text += &format!("DA:{},{}\n", line, count);
+            }
+
+            let hit = lines.values().filter(|count| **count > 0).count();
+            text += &format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), hit);
+        }
+
+        text
+    }
+}
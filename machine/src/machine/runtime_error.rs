@@ -28,7 +28,7 @@ pub enum RuntimeError {
     #[snafu(display("message body does not exist in stack"))]
     MissingMessageBody,
 
-    #[snafu(display("unable to convert sequences of bytes to string"))]
+    #[snafu(display("unable to decode the words as a UTF-16 string"))]
     CannotReadStringFromBytes,
 
     #[snafu(display("cannot load data from memory"))]
@@ -51,4 +51,34 @@ pub enum RuntimeError {
 
     #[snafu(display("index out of bounds. index {index} is over {len}"))]
     IndexOutOfBounds { index: u16, len: u16 },
+
+    #[snafu(display("not inside a stack frame. use `enter` to make one"))]
+    MissingStackFrame,
+
+    #[snafu(display("out of memory. cannot allocate {size} words on the heap"))]
+    OutOfMemory { size: u16 },
+
+    #[snafu(display("double free. the block at {address} is already freed"))]
+    DoubleFree { address: u16 },
+
+    #[snafu(display("invalid free. {address} is not the address of an allocated block"))]
+    InvalidFree { address: u16 },
+
+    #[snafu(display("read fault at pc {pc}. cannot read from address {address}"))]
+    ReadFault { pc: u16, address: u16 },
+
+    #[snafu(display("write fault at pc {pc}. cannot write to address {address}"))]
+    WriteFault { pc: u16, address: u16 },
+
+    #[snafu(display("execute fault. cannot run the instruction at {pc}, which is outside of the code segment"))]
+    ExecuteFault { pc: u16 },
+
+    #[snafu(display("cannot take the square root of a negative value"))]
+    NegativeSquareRoot,
+
+    #[snafu(display("ran out of fuel after {fuel} instructions"))]
+    FuelExhausted { fuel: u64 },
+
+    #[snafu(display("exceeded the limit of {max_cycles} cycles"))]
+    CycleLimitExceeded { max_cycles: u64 },
 }
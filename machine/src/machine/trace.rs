@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use crate::{Message, Op};
+
+/// Word written to memory by an instruction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MemoryWrite {
+    pub address: u16,
+    pub value: u16,
+}
+
+/// Record of a single instruction that the machine has run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct TraceRecord {
+    /// Address of the instruction.
+    pub pc: u16,
+
+    /// Instruction and its operands, such as "push 10".
+    pub op: String,
+
+    /// Data stack before and after the instruction, from the bottom to the top.
+    pub stack_before: Vec<u16>,
+    pub stack_after: Vec<u16>,
+
+    /// Words written outside of the data stack, such as by `store` and `call`.
+    pub writes: Vec<MemoryWrite>,
+
+    /// Messages sent by the instruction.
+    pub messages: Vec<Message>,
+}
+
+impl TraceRecord {
+    /// Describe the instruction with its operands.
+    pub fn describe(op: &Op) -> String {
+        let mut text = op.to_string();
+
+        for value in op.field_values() {
+            text += &format!(" {}", value);
+        }
+
+        text
+    }
+}
+
+/// Receives the trace records from the machine.
+/// Sinks are sendable, so the machines that trace can still be moved across threads.
+pub trait TraceSink: Send {
+    fn record(&mut self, record: TraceRecord);
+}
+
+/// Keeps the most recent records, dropping the oldest once it is full.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RingBuffer {
+    pub capacity: usize,
+    pub records: VecDeque<TraceRecord>,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> RingBuffer {
+        RingBuffer { capacity, records: VecDeque::with_capacity(capacity) }
+    }
+
+    /// Remove and return the records, from the oldest to the newest.
+    pub fn drain(&mut self) -> Vec<TraceRecord> {
+        self.records.drain(..).collect()
+    }
+}
+
+impl TraceSink for RingBuffer {
+    fn record(&mut self, record: TraceRecord) {
+        if self.capacity == 0 { return; }
+
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(record);
+    }
+}
+
+/// Collects every record in memory.
+impl TraceSink for Vec<TraceRecord> {
+    fn record(&mut self, record: TraceRecord) {
+        self.push(record);
+    }
+}
+
+/// Optional hook that the machine sends the trace records to.
+///
+/// The sink is shared, so the caller can keep a handle to read the records while the machine runs.
+/// The tracer is not part of the machine state: it is skipped in serialization and comparison.
+#[derive(Clone, Default)]
+pub struct Tracer(Option<Arc<Mutex<dyn TraceSink>>>);
+
+impl Tracer {
+    pub fn new(sink: Arc<Mutex<dyn TraceSink>>) -> Tracer {
+        Tracer(Some(sink))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn record(&self, record: TraceRecord) {
+        if let Some(sink) = &self.0 {
+            // A sink that panicked while recording can still take the next records.
+            sink.lock().unwrap_or_else(PoisonError::into_inner).record(record);
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tracer({})", if self.is_enabled() { "enabled" } else { "disabled" })
+    }
+}
+
+impl PartialEq for Tracer {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
@@ -1,7 +1,8 @@
 extern crate machine;
 
 use clap::Parser;
-use machine::cli::{compile_to_file, run_from_binary_file, run_from_source, Args, Commands};
+use machine::ExecutionLimits;
+use machine::cli::{compile_to_file, debug_file, disassemble_file, link_files, profile_file, run_from_binary_file, run_from_source, Args, Commands};
 
 fn main() {
     let args = Args::parse();
@@ -12,18 +13,34 @@ fn main() {
     }
 
     let result = match args.command.unwrap() {
-        Commands::Compile { src, out } => compile_to_file(&src, &out),
+        Commands::Compile { src, out, listing, symbols, object } => {
+            compile_to_file(&src, &out, listing.as_deref(), symbols.as_deref(), object)
+        }
+        Commands::Link { objects, out, entry } => link_files(&objects, &out, entry.as_deref()),
         Commands::Run {
             path,
             from_source,
             debug,
+            listing,
+            trace,
+            fuel,
+            max_cycles,
+            protect,
         } => {
+            let limits = ExecutionLimits::new(fuel, max_cycles);
+
             if from_source {
-                run_from_source(&path, debug)
+                run_from_source(&path, debug, protect, trace.as_deref(), limits)
             } else {
-                run_from_binary_file(&path, debug)
+                run_from_binary_file(&path, debug, protect, listing.as_deref(), trace.as_deref(), limits)
             }
         }
+        Commands::Debug { path, from_source, listing } => debug_file(&path, from_source, listing.as_deref()),
+        Commands::Profile { path, from_source, listing, coverage, fuel, max_cycles } => {
+            let limits = ExecutionLimits::new(fuel, max_cycles);
+            profile_file(&path, from_source, listing.as_deref(), coverage.as_deref(), limits)
+        }
+        Commands::Disasm { path, out } => disassemble_file(&path, out.as_deref()),
     };
 
     if let Err(error) = result {
@@ -0,0 +1,308 @@
+use snafu::prelude::*;
+
+use crate::mem::Memory;
+use crate::RuntimeError;
+use crate::machine::runtime_error::{DoubleFreeSnafu, InvalidFreeSnafu, OutOfMemorySnafu};
+
+/// Words in front of each block: its size, then whether it is in use.
+pub const HEAP_HEADER_SIZE: u16 = 2;
+
+const FREE: u16 = 0;
+const USED: u16 = 1;
+
+/// A block of memory in the heap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapBlock {
+    /// Address of the first word after the header, which is given to the program.
+    pub address: u16,
+
+    /// Size of the block in words, excluding the header.
+    pub size: u16,
+
+    pub is_free: bool,
+}
+
+impl HeapBlock {
+    fn header(&self) -> u16 {
+        self.address - HEAP_HEADER_SIZE
+    }
+
+    /// Address of the header of the next block.
+    fn next(&self) -> u16 {
+        self.address.saturating_add(self.size)
+    }
+}
+
+/// First-fit allocator over the heap segment.
+///
+/// The blocks are laid out back to back, each with a header in front of it.
+/// Since the headers live in the memory itself, the heap is saved and restored along with the machine.
+/// A zeroed heap is a single free block that spans the whole segment.
+#[derive(Debug)]
+pub struct HeapManager<'a> {
+    pub mem: &'a mut Memory,
+
+    /// Minimum address of the heap.
+    pub min: u16,
+
+    /// Maximum address of the heap.
+    pub max: u16,
+}
+
+impl<'a> HeapManager<'a> {
+    pub fn new(mem: &'a mut Memory) -> HeapManager<'a> {
+        let (min, max) = (mem.layout.heap_start(), mem.layout.heap_end());
+
+        HeapManager { mem, min, max }
+    }
+
+    /// Words available to a block that spans the whole heap.
+    pub fn capacity(&self) -> u16 {
+        self.max.wrapping_add(1).wrapping_sub(self.min).saturating_sub(HEAP_HEADER_SIZE)
+    }
+
+    fn block_at(&self, header: u16) -> HeapBlock {
+        let size = self.mem.get(header);
+        let address = header + HEAP_HEADER_SIZE;
+
+        if header == self.min && size == 0 {
+            return HeapBlock { address, size: self.capacity(), is_free: true };
+        }
+
+        HeapBlock { address, size, is_free: self.mem.get(header + 1) == FREE }
+    }
+
+    fn set_block(&mut self, block: HeapBlock) {
+        let header = block.header();
+
+        self.mem.set(header, block.size);
+        self.mem.set(header + 1, if block.is_free { FREE } else { USED });
+    }
+
+    /// List the blocks in the heap, from the lowest address.
+    pub fn blocks(&self) -> Vec<HeapBlock> {
+        let mut blocks = vec![];
+        let mut header = self.min;
+
+        // Stop at the end of the heap, or at a header that is overwritten by the program.
+        while header < self.max {
+            let block = self.block_at(header);
+            if block.size == 0 || block.next() > self.max + 1 { break; }
+
+            blocks.push(block);
+            header = block.next();
+        }
+
+        blocks
+    }
+
+    /// Words in use by the allocated blocks, excluding the headers.
+    pub fn used(&self) -> u16 {
+        self.blocks().iter().filter(|b| !b.is_free).map(|b| b.size).sum()
+    }
+
+    /// Allocate a block of the size, and return its address.
+    /// Empty blocks take up a word, so each block has a distinct address.
+    pub fn alloc(&mut self, size: u16) -> Result<u16, RuntimeError> {
+        let size = size.max(1);
+
+        let block = self.blocks().into_iter().find(|b| b.is_free && b.size >= size);
+        let Some(block) = block else {
+            return OutOfMemorySnafu { size }.fail();
+        };
+
+        self.split(HeapBlock { is_free: false, ..block }, size);
+
+        Ok(block.address)
+    }
+
+    /// Free the block at the address, then merge it with the free blocks around it.
+    /// Freeing the null address does nothing.
+    pub fn free(&mut self, address: u16) -> Result<(), RuntimeError> {
+        if address == 0 {
+            return Ok(());
+        }
+
+        let blocks = self.blocks();
+        let index = self.find(&blocks, address)?;
+
+        // Merge with the next block first, so the previous block can take both.
+        let mut block = HeapBlock { is_free: true, ..blocks[index] };
+
+        if let Some(next) = blocks.get(index + 1).filter(|b| b.is_free) {
+            block.size += HEAP_HEADER_SIZE + next.size;
+        }
+
+        if let Some(prev) = index.checked_sub(1).map(|i| blocks[i]).filter(|b| b.is_free) {
+            block = HeapBlock { size: prev.size + HEAP_HEADER_SIZE + block.size, ..prev };
+        }
+
+        self.set_block(block);
+
+        Ok(())
+    }
+
+    /// Resize the block at the address, and return its new address.
+    /// The block grows in place when the next block is free, otherwise its contents are moved to a new block.
+    /// Resizing the null address allocates a new block.
+    pub fn realloc(&mut self, address: u16, size: u16) -> Result<u16, RuntimeError> {
+        if address == 0 {
+            return self.alloc(size);
+        }
+
+        let size = size.max(1);
+        let blocks = self.blocks();
+        let index = self.find(&blocks, address)?;
+        let block = blocks[index];
+
+        // Take the next block, if the two of them are big enough.
+        let mut merged = block;
+
+        if let Some(next) = blocks.get(index + 1).filter(|b| b.is_free) {
+            merged.size += HEAP_HEADER_SIZE + next.size;
+        }
+
+        if merged.size >= size {
+            self.split(merged, size);
+            return Ok(address);
+        }
+
+        // The old block is kept as it is when there is no room for the new one.
+        let data = self.mem.read(address, block.size);
+        let new_address = self.alloc(size)?;
+
+        self.mem.write(new_address, &data);
+        self.free(address)?;
+
+        Ok(new_address)
+    }
+
+    /// Find the allocated block at the address.
+    fn find(&self, blocks: &[HeapBlock], address: u16) -> Result<usize, RuntimeError> {
+        let index = blocks.iter().position(|b| b.header() <= address && address < b.next());
+        let Some(index) = index else {
+            return InvalidFreeSnafu { address }.fail();
+        };
+
+        let block = blocks[index];
+
+        // Freed blocks are merged, so the address could be anywhere in the free block.
+        ensure!(!block.is_free, DoubleFreeSnafu { address });
+        ensure!(block.address == address, InvalidFreeSnafu { address });
+
+        Ok(index)
+    }
+
+    /// Shrink the block to the size, leaving the rest as a free block if it can hold a header and a word.
+    fn split(&mut self, block: HeapBlock, size: u16) {
+        let rest = block.size - size;
+
+        if rest <= HEAP_HEADER_SIZE {
+            self.set_block(block);
+            return;
+        }
+
+        self.set_block(HeapBlock { size, ..block });
+
+        // The block after it is never free, as free blocks are always merged.
+        let free = HeapBlock { address: block.address + size + HEAP_HEADER_SIZE, size: rest - HEAP_HEADER_SIZE, is_free: true };
+        self.set_block(free);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HEAP_START;
+    use crate::RuntimeError::{DoubleFree, InvalidFree, OutOfMemory};
+
+    #[test]
+    fn test_alloc_and_free() -> Result<(), RuntimeError> {
+        let mut mem = Memory::new();
+        let mut h = HeapManager::new(&mut mem);
+
+        let a = h.alloc(4)?;
+        let b = h.alloc(2)?;
+        assert_eq!(a, HEAP_START + HEAP_HEADER_SIZE);
+        assert_eq!(b, a + 4 + HEAP_HEADER_SIZE);
+        assert_eq!(h.used(), 6);
+
+        h.free(a)?;
+        assert_eq!(h.alloc(3)?, a, "first fit should reuse the freed block");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_coalesce() -> Result<(), RuntimeError> {
+        let mut mem = Memory::new();
+        let mut h = HeapManager::new(&mut mem);
+
+        let a = h.alloc(4)?;
+        let b = h.alloc(4)?;
+        let c = h.alloc(4)?;
+
+        h.free(a)?;
+        h.free(c)?;
+        h.free(b)?;
+
+        assert_eq!(h.blocks(), [HeapBlock { address: a, size: h.capacity(), is_free: true }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_errors() -> Result<(), RuntimeError> {
+        let mut mem = Memory::new();
+        let mut h = HeapManager::new(&mut mem);
+
+        let a = h.alloc(4)?;
+        assert_eq!(h.free(a + 1), Err(InvalidFree { address: a + 1 }));
+        assert_eq!(h.free(0x1000), Err(InvalidFree { address: 0x1000 }));
+        assert_eq!(h.free(0), Ok(()));
+
+        h.free(a)?;
+        assert_eq!(h.free(a), Err(DoubleFree { address: a }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_memory() -> Result<(), RuntimeError> {
+        let mut mem = Memory::new();
+        let mut h = HeapManager::new(&mut mem);
+
+        let capacity = h.capacity();
+        assert_eq!(h.alloc(capacity + 1), Err(OutOfMemory { size: capacity + 1 }));
+
+        h.alloc(capacity)?;
+        assert_eq!(h.alloc(1), Err(OutOfMemory { size: 1 }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_realloc() -> Result<(), RuntimeError> {
+        let mut mem = Memory::new();
+        let mut h = HeapManager::new(&mut mem);
+
+        let a = h.alloc(2)?;
+        h.mem.write(a, &[7, 8]);
+
+        // Grow in place into the free space after the block.
+        assert_eq!(h.realloc(a, 10)?, a);
+
+        // Move once the next block is in use.
+        let b = h.alloc(1)?;
+        let moved = h.realloc(a, 20)?;
+        assert_eq!(moved, b + 1 + HEAP_HEADER_SIZE);
+        assert_eq!(h.mem.read(moved, 2), [7, 8]);
+        assert_eq!(h.free(a), Err(DoubleFree { address: a }));
+
+        // Shrinking keeps the address.
+        assert_eq!(h.realloc(moved, 5)?, moved);
+        assert_eq!(h.used(), 6);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use tsify::Tsify;
+use crate::{CALL_STACK_SIZE, CODE_SIZE, DATA_SIZE, HEAP_SIZE, MAPPED_SIZE, MEMORY_SIZE, STACK_SIZE};
+use crate::mem::layout_error::{EmptySegmentSnafu, LayoutError, MemoryTooLargeSnafu, SegmentOverflowSnafu};
+
+/// Sizes of the memory segments of a machine, in words.
+///
+/// The segments are laid out back to back from address zero, in the order of the fields.
+/// Machines with smaller segments take up less memory, which makes the canvas faster to clone and serialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MemoryLayout {
+    pub code_size: u16,
+    pub data_size: u16,
+    pub mapped_size: u16,
+    pub call_stack_size: u16,
+    pub stack_size: u16,
+    pub heap_size: u16,
+}
+
+/// Segments of the memory, in the order they are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemorySegment {
+    Code,
+    Data,
+    Mapped,
+    CallStack,
+    Stack,
+    Heap,
+}
+
+impl MemorySegment {
+    /// Can the program read from the segment by its address, such as with `load`?
+    pub fn is_readable(self) -> bool {
+        !self.is_stack()
+    }
+
+    /// Can the program write to the segment by its address, such as with `store`?
+    /// The code is read-only, so a program cannot overwrite itself.
+    pub fn is_writable(self) -> bool {
+        self != MemorySegment::Code && !self.is_stack()
+    }
+
+    /// The stacks are only touched by the stack operations.
+    fn is_stack(self) -> bool {
+        matches!(self, MemorySegment::CallStack | MemorySegment::Stack)
+    }
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        MemoryLayout {
+            code_size: CODE_SIZE,
+            data_size: DATA_SIZE,
+            mapped_size: MAPPED_SIZE,
+            call_stack_size: CALL_STACK_SIZE,
+            stack_size: STACK_SIZE,
+            heap_size: HEAP_SIZE,
+        }
+    }
+}
+
+impl MemoryLayout {
+    /// Total words of memory used by the segments.
+    pub fn size(&self) -> usize {
+        self.heap_start() as usize + self.heap_size as usize
+    }
+
+    /// The segments must fit in the address space, and the code and stacks cannot be empty.
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        let size: usize = [self.code_size, self.data_size, self.mapped_size, self.call_stack_size, self.stack_size, self.heap_size]
+            .iter().map(|size| *size as usize).sum();
+
+        ensure!(size <= MEMORY_SIZE as usize, MemoryTooLargeSnafu { size });
+
+        for (segment, size) in [("code", self.code_size), ("call stack", self.call_stack_size), ("stack", self.stack_size)] {
+            ensure!(size > 0, EmptySegmentSnafu { segment });
+        }
+
+        Ok(())
+    }
+
+    /// The code and data of the program must fit in their segments.
+    pub fn check_program(&self, code_size: usize, data_size: usize) -> Result<(), LayoutError> {
+        ensure!(code_size <= self.code_size as usize, SegmentOverflowSnafu { segment: "code", size: code_size, max: self.code_size });
+        ensure!(data_size <= self.data_size as usize, SegmentOverflowSnafu { segment: "data", size: data_size, max: self.data_size });
+
+        Ok(())
+    }
+
+    /// Segment that the address is in. Addresses past the end of the memory are not in any segment.
+    pub fn segment_of(&self, address: u16) -> Option<MemorySegment> {
+        let segments = [
+            (MemorySegment::Code, self.data_start()),
+            (MemorySegment::Data, self.mapped_start()),
+            (MemorySegment::Mapped, self.call_stack_start()),
+            (MemorySegment::CallStack, self.stack_start()),
+            (MemorySegment::Stack, self.heap_start()),
+        ];
+
+        if let Some((segment, _)) = segments.iter().find(|(_, end)| address < *end) {
+            return Some(*segment);
+        }
+
+        ((address as usize) < self.size()).then_some(MemorySegment::Heap)
+    }
+
+    /// Sizes of the segments in the binary format, in the order they are laid out.
+    pub fn section_words(&self) -> Vec<u16> {
+        vec![self.code_size, self.data_size, self.mapped_size, self.call_stack_size, self.stack_size, self.heap_size]
+    }
+
+    /// Read the layout from the binary format. The layout is not validated.
+    pub fn from_section_words(words: &[u16]) -> Option<MemoryLayout> {
+        let [code_size, data_size, mapped_size, call_stack_size, stack_size, heap_size] = *words else {
+            return None;
+        };
+
+        Some(MemoryLayout { code_size, data_size, mapped_size, call_stack_size, stack_size, heap_size })
+    }
+
+    pub fn code_start(&self) -> u16 {
+        0
+    }
+
+    pub fn code_end(&self) -> u16 {
+        self.code_start() + self.code_size - 1
+    }
+
+    pub fn data_start(&self) -> u16 {
+        self.code_start() + self.code_size
+    }
+
+    pub fn data_end(&self) -> u16 {
+        (self.data_start() + self.data_size).wrapping_sub(1)
+    }
+
+    pub fn mapped_start(&self) -> u16 {
+        self.data_start() + self.data_size
+    }
+
+    pub fn mapped_end(&self) -> u16 {
+        (self.mapped_start() + self.mapped_size).wrapping_sub(1)
+    }
+
+    pub fn call_stack_start(&self) -> u16 {
+        self.mapped_start() + self.mapped_size
+    }
+
+    pub fn call_stack_end(&self) -> u16 {
+        self.call_stack_start() + self.call_stack_size - 1
+    }
+
+    pub fn stack_start(&self) -> u16 {
+        self.call_stack_start() + self.call_stack_size
+    }
+
+    pub fn stack_end(&self) -> u16 {
+        self.stack_start() + self.stack_size - 1
+    }
+
+    pub fn heap_start(&self) -> u16 {
+        self.stack_start() + self.stack_size
+    }
+
+    pub fn heap_end(&self) -> u16 {
+        (self.heap_start() + self.heap_size).wrapping_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CALL_STACK_START, DATA_START, HEAP_END, HEAP_START, MAPPED_START, STACK_END, STACK_START};
+
+    #[test]
+    fn test_default_layout() {
+        let layout = MemoryLayout::default();
+
+        assert_eq!(layout.data_start(), DATA_START);
+        assert_eq!(layout.mapped_start(), MAPPED_START);
+        assert_eq!(layout.call_stack_start(), CALL_STACK_START);
+        assert_eq!((layout.stack_start(), layout.stack_end()), (STACK_START, STACK_END));
+        assert_eq!((layout.heap_start(), layout.heap_end()), (HEAP_START, HEAP_END));
+        assert_eq!(layout.size(), MEMORY_SIZE as usize);
+        assert_eq!(layout.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_segment_of() {
+        let layout = MemoryLayout::default();
+
+        assert_eq!(layout.segment_of(0), Some(MemorySegment::Code));
+        assert_eq!(layout.segment_of(DATA_START - 1), Some(MemorySegment::Code));
+        assert_eq!(layout.segment_of(DATA_START), Some(MemorySegment::Data));
+        assert_eq!(layout.segment_of(CALL_STACK_START), Some(MemorySegment::CallStack));
+        assert_eq!(layout.segment_of(STACK_END), Some(MemorySegment::Stack));
+        assert_eq!(layout.segment_of(HEAP_END), Some(MemorySegment::Heap));
+        assert_eq!(layout.segment_of(HEAP_END + 1), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let layout = MemoryLayout { stack_size: 0xFFFF, ..MemoryLayout::default() };
+        assert!(matches!(layout.validate(), Err(LayoutError::MemoryTooLarge { .. })));
+
+        let layout = MemoryLayout { call_stack_size: 0, ..MemoryLayout::default() };
+        assert_eq!(layout.validate(), Err(LayoutError::EmptySegment { segment: "call stack".into() }));
+    }
+
+    #[test]
+    fn test_section_words() {
+        let layout = MemoryLayout { heap_size: 0x100, ..MemoryLayout::default() };
+
+        assert_eq!(MemoryLayout::from_section_words(&layout.section_words()), Some(layout));
+        assert_eq!(MemoryLayout::from_section_words(&[0x1000, 0x1000]), None);
+    }
+}
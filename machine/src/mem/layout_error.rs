@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use tsify::Tsify;
+
+#[derive(Debug, Snafu, Serialize, Deserialize, PartialEq, Clone, Tsify)]
+#[snafu(visibility(pub))]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi, namespace)]
+pub enum LayoutError {
+    #[snafu(display("memory layout takes up {size} words, which is more than the address space"))]
+    MemoryTooLarge { size: usize },
+
+    #[snafu(display("the {segment} segment cannot be empty"))]
+    EmptySegment { segment: String },
+
+    #[snafu(display("program needs {size} words in the {segment} segment, which only has {max} words"))]
+    SegmentOverflow { segment: String, size: usize, max: u16 },
+}
@@ -1,78 +1,272 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
-use crate::{compile_to_bytecode, Symbols, Op, CALL_STACK_START, CODE_START, MEMORY_SIZE, STACK_START, DATA_START, STACK_END, CALL_STACK_END};
+use crate::{compile_to_bytecode, LayoutError, MemoryLayout, MemoryWrite, Symbols, Op};
+
+/// Words in each page of the memory.
+pub const PAGE_SIZE: usize = 0x100;
 
 /**
  * Memory defines a fixed-size memory area for the program.
+ *
+ * The memory is split into pages, which are shared between the clones of the memory
+ * until one of them writes to the page. Pages that are never written to are not allocated,
+ * and read as zero. This keeps snapshots and clones of the machine cheap.
  */
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "MemoryData", into = "MemoryData")]
 pub struct Memory {
-    pub buffer: Vec<u16>,
+    pages: Vec<Option<Arc<Vec<u16>>>>,
+
+    /// Where each segment is, which decides the size of the memory.
+    pub layout: MemoryLayout,
+
+    /// Writes since the journal is started, used to trace the instructions.
+    pub journal: Option<Vec<MemoryWrite>>,
+}
+
+/// Serialized form of the memory, which only has the pages that are written to.
+#[derive(Serialize, Deserialize)]
+struct MemoryData {
+    #[serde(default)]
+    layout: MemoryLayout,
+
+    /// Words of each written page, by the index of the page.
+    #[serde(default)]
+    pages: BTreeMap<usize, Vec<u16>>,
+
+    /// Flat buffer of the whole memory, from snapshots saved before the memory is paged.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buffer: Vec<u16>,
+}
+
+impl From<Memory> for MemoryData {
+    fn from(mem: Memory) -> Self {
+        let pages = mem.pages.iter().enumerate()
+            .filter_map(|(index, page)| Some((index, page.as_ref()?.to_vec())))
+            .collect();
+
+        MemoryData { layout: mem.layout, pages, buffer: vec![] }
+    }
+}
+
+impl From<MemoryData> for Memory {
+    fn from(data: MemoryData) -> Self {
+        let mut mem = Memory::with_layout(data.layout);
+
+        for (index, words) in data.pages {
+            mem.write_words(index * PAGE_SIZE, &words);
+        }
+
+        mem.write_words(0, &data.buffer);
+        mem
+    }
+}
+
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        self.layout == other.layout && (0..self.page_count()).all(|index| {
+            self.shares_page(other, index) || self.read_page(index) == other.read_page(index)
+        })
+    }
 }
 
 impl Memory {
     pub fn new() -> Memory {
+        Memory::with_layout(MemoryLayout::default())
+    }
+
+    pub fn with_layout(layout: MemoryLayout) -> Memory {
         Memory {
-            buffer: vec![0; MEMORY_SIZE as usize],
+            pages: vec![None; (layout.size() + PAGE_SIZE - 1) / PAGE_SIZE],
+            layout,
+            journal: None,
+        }
+    }
+
+    /// Total words in the memory.
+    pub fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Number of pages in the memory. The last page is shorter when the size is not a multiple of the page size.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Number of pages that are written to, and take up space.
+    pub fn allocated_pages(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+
+    /// Are the pages at the index the same in both memories, without comparing their words?
+    /// Pages stay shared after a clone until either memory writes to them.
+    pub fn shares_page(&self, other: &Memory, index: usize) -> bool {
+        match (self.pages.get(index), other.pages.get(index)) {
+            (Some(Some(a)), Some(Some(b))) => Arc::ptr_eq(a, b),
+            (Some(None), Some(None)) => true,
+            _ => false,
+        }
+    }
+
+    /// Words in the page at the index. Pages past the end of the memory are empty.
+    pub fn read_page(&self, index: usize) -> Vec<u16> {
+        match self.pages.get(index) {
+            Some(Some(page)) => page.to_vec(),
+            Some(None) => vec![0; self.page_len(index)],
+            None => vec![],
+        }
+    }
+
+    fn page_len(&self, index: usize) -> usize {
+        self.size().saturating_sub(index * PAGE_SIZE).min(PAGE_SIZE)
+    }
+
+    /// Page at the index for writing, which is allocated or copied if it is shared.
+    fn page_mut(&mut self, index: usize) -> &mut Vec<u16> {
+        let len = self.page_len(index);
+        let page = self.pages[index].get_or_insert_with(|| Arc::new(vec![0; len]));
+
+        Arc::make_mut(page)
+    }
+
+    fn set_word(&mut self, addr: usize, val: u16) {
+        let (index, offset) = (addr / PAGE_SIZE, addr % PAGE_SIZE);
+
+        // Zeroes written to an empty page do not need to allocate it.
+        if val == 0 && matches!(self.pages.get(index), Some(None)) { return; }
+
+        self.page_mut(index)[offset] = val;
+    }
+
+    fn write_words(&mut self, addr: usize, data: &[u16]) {
+        let end = (addr + data.len()).min(self.size());
+
+        for (offset, value) in data.iter().take(end.saturating_sub(addr)).enumerate() {
+            self.set_word(addr + offset, *value);
         }
     }
 
+    /// Addresses past the end of the memory are ignored.
     pub fn set(&mut self, addr: u16, val: u16) {
-        self.buffer[addr as usize] = val;
+        if addr as usize >= self.size() { return; }
+        self.set_word(addr as usize, val);
+
+        if let Some(journal) = &mut self.journal {
+            journal.push(MemoryWrite { address: addr, value: val });
+        }
+    }
+
+    /// Record the writes until the journal is taken.
+    pub fn start_journal(&mut self) {
+        self.journal = Some(vec![]);
+    }
+
+    /// Stop recording, and return the writes since the journal is started.
+    pub fn take_journal(&mut self) -> Vec<MemoryWrite> {
+        self.journal.take().unwrap_or_default()
     }
 
     /// Reset the entire memory to zero.
     pub fn reset(&mut self) {
-        self.buffer.fill(0)
+        self.pages.fill(None)
     }
 
     pub fn reset_range(&mut self, from: u16, to: u16) {
-        self.buffer[(from as usize)..(to as usize)].fill(0);
+        self.fill_zero(from as usize, to as usize);
+    }
+
+    /// Zero the words from the start to the end. Pages that are zeroed as a whole are released.
+    fn fill_zero(&mut self, start: usize, end: usize) {
+        let end = end.min(self.size());
+
+        for index in start / PAGE_SIZE..self.page_count() {
+            let page_start = index * PAGE_SIZE;
+            if page_start >= end { break; }
+
+            let page_end = page_start + self.page_len(index);
+
+            if start <= page_start && page_end <= end {
+                self.pages[index] = None;
+            } else if self.pages[index].is_some() {
+                let (from, to) = (start.max(page_start) - page_start, end.min(page_end) - page_start);
+                self.page_mut(index)[from..to].fill(0);
+            }
+        }
     }
 
     /// Reset the stack and call stack memory.
     pub fn reset_stacks(&mut self) {
-        self.reset_range(CALL_STACK_START, CALL_STACK_END);
-        self.reset_range(STACK_START, STACK_END);
+        let layout = self.layout;
+
+        self.reset_range(layout.call_stack_start(), layout.call_stack_end());
+        self.reset_range(layout.stack_start(), layout.stack_end());
+    }
+
+    /// Free every block in the heap.
+    pub fn reset_heap(&mut self) {
+        self.fill_zero(self.layout.heap_start() as usize, self.size());
     }
 
+    /// Addresses past the end of the memory read as zero.
     pub fn get(&self, addr: u16) -> u16 {
-        self.buffer[addr as usize]
+        let (index, offset) = (addr as usize / PAGE_SIZE, addr as usize % PAGE_SIZE);
+
+        match self.pages.get(index) {
+            Some(Some(page)) => page.get(offset).copied().unwrap_or(0),
+            _ => 0,
+        }
     }
 
     pub fn read(&self, addr: u16, count: u16) -> Vec<u16> {
-        let start = addr as usize;
-
-        self.buffer[start..(start + count as usize)].into()
+        (0..count as usize)
+            .map(|offset| u16::try_from(addr as usize + offset).map_or(0, |addr| self.get(addr)))
+            .collect()
     }
 
+    /// Words past the end of the address space are dropped, the same as `set` ignores them.
     pub fn write(&mut self, addr: u16, data: &[u16]) {
         for (offset, value) in data.iter().enumerate() {
-            self.buffer[addr as usize + offset] = *value;
+            let Some(address) = u16::try_from(offset).ok().and_then(|offset| addr.checked_add(offset)) else { break; };
+
+            self.set(address, *value);
         }
     }
 
     pub fn read_code(&self, count: u16) -> Vec<u16> {
-        self.read(CODE_START, count)
+        self.read(self.layout.code_start(), count)
     }
 
     pub fn read_stack(&self, count: u16) -> Vec<u16> {
-        self.read(STACK_START, count)
+        self.read(self.layout.stack_start(), count)
     }
 
     pub fn read_call_stack(&self, count: u16) -> Vec<u16> {
-        self.read(CALL_STACK_START, count)
+        self.read(self.layout.call_stack_start(), count)
     }
 
     pub fn read_data(&self, count: u16) -> Vec<u16> {
-        self.read(DATA_START, count)
+        self.read(self.layout.data_start(), count)
     }
 
     pub fn load_code(&mut self, ops: Vec<Op>) {
-        self.write(CODE_START, &compile_to_bytecode(ops))
+        self.write(self.layout.code_start(), &compile_to_bytecode(ops))
     }
 
     pub fn load_symbols(&mut self, symbols: Symbols) {
-        self.write(DATA_START, &symbols.bytes());
+        self.write(self.layout.data_start(), &symbols.bytes());
+    }
+
+    /// Load the code and data of the program, once they are checked to fit in their segments.
+    pub fn load_program(&mut self, ops: Vec<Op>, symbols: Symbols) -> Result<(), LayoutError> {
+        let code = compile_to_bytecode(ops);
+        let data = symbols.bytes();
+        self.layout.check_program(code.len(), data.len())?;
+
+        self.write(self.layout.code_start(), &code);
+        self.write(self.layout.data_start(), &data);
+
+        Ok(())
     }
 }
 
@@ -80,6 +274,7 @@ impl Memory {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{CALL_STACK_START, CODE_START, STACK_START};
 
     #[test]
     fn test_memset() {
@@ -98,11 +293,21 @@ mod tests {
         assert_eq!(m.read(0x03, 3), [1, 2, 3]);
     }
 
+    #[test]
+    fn test_write_past_the_end() {
+        let mut m = Memory::new();
+
+        // The last word is 0xFFFE, and 0xFFFF is the end of the address space.
+        m.write(0xFFFE, &[1, 2, 3]);
+        assert_eq!(m.read(0xFFFE, 2), [1, 0]);
+        assert_eq!(m.get(0), 0, "writes do not wrap around to the start");
+    }
+
     #[test]
     fn test_load_code() {
         let mut m = Memory::new();
         m.load_code(vec![Op::Push(5), Op::Push(10)]);
-        assert_eq!(&m.buffer[0..4], [0x01, 5, 0x01, 10])
+        assert_eq!(m.read(0, 4), [0x01, 5, 0x01, 10])
     }
 
     #[test]
@@ -121,4 +326,45 @@ mod tests {
         assert_eq!(m.read_stack(3), [0, 0, 0]);
         assert_eq!(m.read_call_stack(3), [0, 0, 0]);
     }
+
+    #[test]
+    fn test_copy_on_write() {
+        let mut a = Memory::new();
+        a.write(CODE_START, &[1, 2, 3]);
+        assert_eq!(a.allocated_pages(), 1);
+
+        let mut b = a.clone();
+        assert!(b.shares_page(&a, 0));
+
+        b.set(CODE_START, 9);
+        assert!(!b.shares_page(&a, 0));
+        assert_eq!(a.read_code(3), [1, 2, 3]);
+        assert_eq!(b.read_code(3), [9, 2, 3]);
+    }
+
+    #[test]
+    fn test_reset_releases_pages() {
+        let mut m = Memory::new();
+        m.write(PAGE_SIZE as u16 - 1, &[1, 2, 3]);
+        assert_eq!(m.allocated_pages(), 2);
+
+        // The second page is zeroed as a whole, while the first is only partly zeroed.
+        m.reset_range(PAGE_SIZE as u16 - 1, PAGE_SIZE as u16 * 2);
+        assert_eq!(m.allocated_pages(), 1);
+        assert_eq!(m.read(PAGE_SIZE as u16 - 1, 3), [0, 0, 0]);
+        assert_eq!(m, Memory::new());
+    }
+
+    #[test]
+    fn test_serialize_written_pages() {
+        let mut m = Memory::new();
+        m.write(STACK_START, &[4, 5, 6]);
+
+        let json = serde_json::to_value(&m).unwrap();
+        assert_eq!(json["pages"].as_object().map(|pages| pages.len()), Some(1));
+
+        let restored: Memory = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, m);
+        assert_eq!(restored.read_stack(3), [4, 5, 6]);
+    }
 }
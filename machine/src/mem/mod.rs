@@ -1,8 +1,14 @@
+pub mod heap;
+pub mod layout;
+pub mod layout_error;
 pub mod memory;
 pub mod stack;
 pub mod segments;
 pub mod string;
 
+pub use self::heap::*;
+pub use self::layout::*;
+pub use self::layout_error::LayoutError;
 pub use self::memory::*;
 pub use self::stack::*;
 pub use self::segments::*;
@@ -1,3 +1,5 @@
+// Segments of the default memory layout. Each machine can have its own `MemoryLayout`.
+
 /// Total memory available.
 pub const MEMORY_SIZE: u16 = 0xFFFF;
 
@@ -6,6 +8,7 @@ pub const CODE_SIZE: u16 = 0x1000;
 pub const DATA_SIZE: u16 = 0x1000;
 pub const MAPPED_SIZE: u16 = 0x2000;
 pub const CALL_STACK_SIZE: u16 = 0x100;
+pub const HEAP_SIZE: u16 = 0x4000;
 
 // Code segment
 pub const CODE_START: u16 = 0x0000;
@@ -23,6 +26,11 @@ pub const MAPPED_END: u16 = MAPPED_START + MAPPED_SIZE - 1;
 pub const CALL_STACK_START: u16 = MAPPED_END + 1;
 pub const CALL_STACK_END: u16 = CALL_STACK_START + CALL_STACK_SIZE - 1;
 
-// Stack segment
+// Stack segment, which takes up the memory between the call stack and the heap.
 pub const STACK_START: u16 = CALL_STACK_END + 1;
-pub const STACK_END: u16 = MEMORY_SIZE - 1;
+pub const STACK_END: u16 = HEAP_START - 1;
+pub const STACK_SIZE: u16 = STACK_END - STACK_START + 1;
+
+// Heap segment, at the end of the memory.
+pub const HEAP_START: u16 = MEMORY_SIZE - HEAP_SIZE;
+pub const HEAP_END: u16 = MEMORY_SIZE - 1;
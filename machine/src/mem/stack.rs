@@ -3,7 +3,7 @@ use snafu::prelude::*;
 use crate::mem::Memory;
 use crate::register::{Register, Register::SP, Registers};
 
-use crate::{RuntimeError, STACK_END, STACK_START};
+use crate::RuntimeError;
 use crate::machine::runtime_error::{StackOverflowSnafu, StackUnderflowSnafu};
 
 #[derive(Debug)]
@@ -26,7 +26,9 @@ pub struct StackManager<'a> {
 
 impl<'a> StackManager<'a> {
     pub fn new(mem: &'a mut Memory, reg: &'a mut Registers) -> StackManager<'a> {
-        StackManager { mem, reg, min: STACK_START, max: STACK_END, sp: SP, is_debug: false }
+        let (min, max) = (mem.layout.stack_start(), mem.layout.stack_end());
+
+        StackManager { mem, reg, min, max, sp: SP, is_debug: false }
     }
 
     pub fn top(&self) -> u16 {
@@ -93,6 +95,42 @@ impl<'a> StackManager<'a> {
         Ok(())
     }
 
+    /// Apply the function to the top value, as a signed integer.
+    pub fn apply_signed<F>(&mut self, f: F) -> Result<(), RuntimeError>
+        where F: FnOnce(i16) -> Result<i16, RuntimeError> {
+        self.apply(|a| f(a as i16).map(|v| v as u16))
+    }
+
+    /// Apply the function to the top two values, as signed integers.
+    pub fn apply_two_signed<F>(&mut self, f: F) -> Result<(), RuntimeError>
+        where F: FnOnce(i16, i16) -> Result<i16, RuntimeError> {
+        self.apply_two(|a, b| f(a as i16, b as i16).map(|v| v as u16))
+    }
+
+    /// Push the 32-bit value as two words, with the high word below the low word.
+    pub fn push32(&mut self, val: u32) -> Result<(), RuntimeError> {
+        self.push((val >> 16) as u16)?;
+        self.push(val as u16)
+    }
+
+    /// Pop the 32-bit value from the top two words.
+    pub fn pop32(&mut self) -> Result<u32, RuntimeError> {
+        let low = self.pop()? as u32;
+        let high = self.pop()? as u32;
+
+        Ok((high << 16) | low)
+    }
+
+    /// Apply the function to the top two 32-bit values.
+    pub fn apply_two32<F>(&mut self, f: F) -> Result<(), RuntimeError>
+        where F: FnOnce(u32, u32) -> Result<u32, RuntimeError> {
+        let b = self.pop32()?;
+        let a = self.pop32()?;
+
+        let value = f(a, b)?;
+        self.push32(value)
+    }
+
     pub fn len(&self) -> u16 {
         let v = (self.top().checked_add(1)).unwrap_or(u16::MAX);
         v.checked_sub(self.min).unwrap_or(v)
@@ -102,6 +140,7 @@ impl<'a> StackManager<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::STACK_END;
     use crate::machine::Machine;
 
     #[test]
@@ -1,6 +1,6 @@
 extern crate snafu;
 
-use crate::mem::{DATA_END, DATA_START, Memory};
+use crate::mem::Memory;
 use crate::RuntimeError;
 use crate::RuntimeError::CannotReadStringFromBytes;
 
@@ -11,7 +11,9 @@ pub struct StringManager<'a> {
 
 impl<'a> StringManager<'a> {
     fn new(mem: &'a mut Memory) -> StringManager<'a> {
-        StringManager { mem, top: DATA_START }
+        let top = mem.layout.data_start();
+
+        StringManager { mem, top }
     }
 
     /// Add the given data to the data section.
@@ -29,12 +31,9 @@ impl<'a> StringManager<'a> {
     }
 
 
-    /// Get the string at the given address.
+    /// Decode the UTF-16 words of a string.
     pub fn get_str_from_bytes(&self, v16: Vec<u16>) -> Result<String, RuntimeError> {
-        // TODO: Properly decode high UTF-8 bytes such as emojis.
-        let v8: Vec<u8> = v16.iter().map(|&x| x as u8).collect();
-
-        String::from_utf8(v8).map_err(|_| CannotReadStringFromBytes)
+        u16_to_str(&v16)
     }
 
     /// Get the string bytes until the null terminator.
@@ -44,7 +43,7 @@ impl<'a> StringManager<'a> {
 
         for i in addr.. {
             // We've reached the end of the data section.
-            if i > DATA_END { break; }
+            if i > self.mem.layout.data_end() { break; }
 
             // Read the value at the current address.
             let v = self.mem.get(i);
@@ -57,6 +56,16 @@ impl<'a> StringManager<'a> {
 
         data
     }
+
+    /// Get the string words until the null terminator, which can be anywhere in the memory, such as on the heap.
+    pub fn read_str(&self, addr: u16) -> Vec<u16> {
+        let end = self.mem.size();
+
+        (addr as usize..end)
+            .map(|i| self.mem.get(i as u16))
+            .take_while(|v| *v != 0x00)
+            .collect()
+    }
 }
 
 pub trait WithStringManager {
@@ -69,8 +78,40 @@ impl WithStringManager for Memory {
     }
 }
 
+/// Encode the string as null-terminated UTF-16, where characters outside of the BMP take up two words.
 pub fn str_to_u16(s: &str) -> Vec<u16> {
-    let mut v: Vec<u16> = s.chars().map(|c| c as u16).collect();
+    let mut v: Vec<u16> = s.encode_utf16().collect();
     v.push(0x00);
     v
+}
+
+/// Decode the UTF-16 words, without the null terminator. Unpaired surrogates cannot be decoded.
+pub fn u16_to_str(v16: &[u16]) -> Result<String, RuntimeError> {
+    String::from_utf16(v16).map_err(|_| CannotReadStringFromBytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unicode_round_trip() -> Result<(), RuntimeError> {
+        let words = str_to_u16("héllo 🦀");
+        assert_eq!(words.len(), 9, "the crab takes up a surrogate pair");
+
+        assert_eq!(u16_to_str(&words[..words.len() - 1])?, "héllo 🦀");
+        assert_eq!(u16_to_str(&[0xD83E]), Err(CannotReadStringFromBytes));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_str() {
+        let mut mem = Memory::new();
+        let heap = mem.layout.heap_start();
+        mem.write(heap, &str_to_u16("hi"));
+
+        assert_eq!(mem.string().read_str(heap), [104, 105]);
+        assert!(mem.string().get_str_bytes(heap).is_empty(), "load_string only reads from the data segment");
+    }
 }
\ No newline at end of file
@@ -4,23 +4,17 @@ pub fn bytes_to_ops(bytes: Vec<u16>) -> Vec<Op> {
     let mut pc = 0;
     let mut ops = vec![];
 
-    let cycle = 0;
-
-    loop {
-        if pc >= bytes.len() { break; }
-        if cycle > 300 { break; }
-
+    while pc < bytes.len() {
         let op: Op = bytes[pc].into();
         if op == Op::Eof { break; }
 
+        // Missing operands at the end of the bytecode are read as zeros.
         let op = op.with_arg(|| {
             pc += 1;
-            bytes[pc]
+            bytes.get(pc).copied().unwrap_or(0)
         });
 
         pc += 1;
-        if op == Op::Noop { continue; }
-
         ops.push(op);
     }
 
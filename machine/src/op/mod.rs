@@ -122,7 +122,7 @@ pub enum Op {
 
     /// Pause the execution for X milliseconds
     SleepMs(u16),
-    
+
     /// Pause the execution for X ticks
     SleepTick(u16),
 
@@ -131,12 +131,197 @@ pub enum Op {
 
     /// End-of-file marker.
     Eof,
+
+    // New instructions are appended after this point, so the existing opcodes keep their numbers.
+
+    /// Signed addition, subtraction, multiplication, division and modulo.
+    /// The values are 16-bit two's complement integers, from -32768 to 32767.
+    AddS,
+    SubS,
+    MulS,
+    DivS,
+    ModS,
+
+    /// Negates the signed value at the top of the stack.
+    /// [1, 5] -> [1, -5]
+    Neg,
+
+    /// Addition, subtraction and multiplication that wrap around on overflow.
+    /// They work the same for both signed and unsigned values.
+    AddWrap,
+    SubWrap,
+    MulWrap,
+
+    /// Addition, subtraction and multiplication that clamp the unsigned result to 0..=65535.
+    AddSat,
+    SubSat,
+    MulSat,
+
+    /// Addition, subtraction and multiplication that clamp the signed result to -32768..=32767.
+    AddSatS,
+    SubSatS,
+    MulSatS,
+
+    /// Signed comparisons.
+    LessThanS,
+    LessThanOrEqualS,
+    GreaterThanS,
+    GreaterThanOrEqualS,
+
+    /// Arithmetic Right Shift, which keeps the sign bit of the signed value.
+    ArithmeticRightShift,
+
+    /// Push the 32-bit value as two words, with the high word below the low word.
+    /// The operand is written as one value, e.g. `push32 100000`, or as the high word then the low word, e.g. `push32 0x0001 0x86A0`.
+    /// [] -> [0x0001, 0x86A0]
+    Push32(u32),
+
+    /// Push the 32-bit value from the address, with the high word at the address and the low word after it.
+    Load32(u16),
+
+    /// Pop the 32-bit value from the stack and store it into the address.
+    Store32(u16),
+
+    /// Addition, subtraction and multiplication of the top two 32-bit values.
+    /// [a_hi, a_lo, b_hi, b_lo] -> [c_hi, c_lo]
+    Add32,
+    Sub32,
+    Mul32,
+
+    /// Divides the top two 32-bit values, then pushes the quotient and the remainder.
+    /// [a_hi, a_lo, b_hi, b_lo] -> [quotient_hi, quotient_lo, remainder_hi, remainder_lo]
+    #[strum(serialize = "divmod32")]
+    DivMod32,
+
+    /// Compares the top two 32-bit values. Pushes -1 if a < b, 0 if a == b, or 1 if a > b.
+    /// [a_hi, a_lo, b_hi, b_lo] -> [ordering]
+    Cmp32,
+
+    /// Multiplication and division of signed Q8.8 fixed-point values, which have 8 fractional bits.
+    /// Fixed-point values are written as `1.5q`, and are added and subtracted with `add_s` and `sub_s`.
+    /// The results of the fixed-point instructions are rounded to the nearest value, with halves away from zero.
+    #[strum(serialize = "fmul")]
+    FMul,
+
+    #[strum(serialize = "fdiv")]
+    FDiv,
+
+    /// Sine of the fixed-point angle in radians.
+    /// [1.5708q] -> [1.0q]
+    #[strum(serialize = "fsin")]
+    FSin,
+
+    /// Square root of the fixed-point value.
+    /// [2.25q] -> [1.5q]
+    #[strum(serialize = "fsqrt")]
+    FSqrt,
+
+    /// Linear interpolation between a and b by the fixed-point ratio t, from 0.0q to 1.0q.
+    /// [a, b, t] -> [a + (b - a) * t]
+    Lerp,
+
+    /// Pop the address from the stack, then push the value at the address.
+    /// [address] -> [value]
+    LoadI,
+
+    /// Pop the address and the value from the stack, then store the value at the address.
+    /// [value, address] -> []
+    StoreI,
+
+    /// Load the value at the address, then advance the pointer to the next word.
+    /// [address] -> [address + 1, value]
+    LoadIInc,
+
+    /// Store the value at the address, then advance the pointer to the next word.
+    /// [value, address] -> [address + 1]
+    StoreIInc,
+
+    /// Add the operand to the pointer, such as to move to the next node of a list.
+    /// inc_by(3) [address] -> [address + 3]
+    IncBy(u16),
+
+    /// Push the nth local variable of the stack frame onto the stack.
+    LoadLocal(u16),
+
+    /// Pop the value from the stack and store it into the nth local variable.
+    StoreLocal(u16),
+
+    /// Make a stack frame with n locals for the function, which starts out as zeros.
+    ///
+    /// The calling convention is:
+    ///
+    /// 1. The caller pushes the arguments from the last to the first, then calls the function.
+    /// 2. The function runs `enter n` to make room for n locals, which are read with `load_local`.
+    ///    The arguments are read with `load_arg`, where the first argument is `load_arg 0`.
+    /// 3. The function pushes its result, then runs `leave` and `return`.
+    /// 4. The caller removes the arguments below the result, such as with `nip`.
+    ///
+    /// The frame lives on the call stack: `[return address, caller's BP, SP at entry, locals...]`.
+    /// The base pointer (BP) points at the saved SP, and the locals come right after it.
+    Enter(u16),
+
+    /// Remove the stack frame made by `enter`, restoring the frame of the caller.
+    Leave,
+
+    /// Push the nth argument of the function onto the stack.
+    /// The arguments are counted down from the top of the stack when the function enters its frame.
+    LoadArg(u16),
+
+    /// Pop the value from the stack and store it into the nth argument.
+    StoreArg(u16),
+
+    /// Allocate n words on the heap, then push the address of the block.
+    Alloc(u16),
+
+    /// Pop the address from the stack, then free the block at the address.
+    Free,
+
+    /// Resize the block to the new size, then push its new address.
+    /// The contents are moved to the new address when the block cannot grow in place.
+    /// [address, size] -> [address]
+    Realloc,
+
+    /// Push the length of the null-terminated string at the address, in UTF-16 words.
+    /// [address] -> [length]
+    #[strum(serialize = "strlen")]
+    StrLen,
+
+    /// Compares the null-terminated strings at the two addresses, word by word.
+    /// Pushes -1 if a < b, 0 if a == b, or 1 if a > b.
+    /// [a, b] -> [ordering]
+    #[strum(serialize = "strcmp")]
+    StrCmp,
+
+    /// Push the decimal digits of the value as characters, which are printed with `print`.
+    /// The null terminator is not pushed, the same as `load_string`.
+    /// [42] -> ['4', '2']
+    Itoa,
+
+    /// Push the decimal digits of the signed value, after a minus sign if it is negative.
+    /// [-5] -> ['-', '5']
+    ItoaS,
 }
 
 impl Op {
     pub fn opcode(self) -> u16 {
         self.index() as u16
     }
+
+    /// Cycles spent to run the instruction.
+    /// Fetching each word of the instruction takes a cycle, and so does each word moved by `read` and `write`.
+    pub fn cycles(self) -> u64 {
+        let transfer = match self {
+            Op::Read(size) | Op::Write(size) => size as u64,
+            _ => 0,
+        };
+
+        1 + self.arity() as u64 + transfer
+    }
+
+    /// Does the instruction take a 32-bit operand, such as `push32 100000`?
+    pub fn has_double_word_operand(self) -> bool {
+        matches!(self, Op::Push32(..))
+    }
 }
 
 impl From<u16> for Op {
@@ -176,4 +361,11 @@ mod tests {
         assert_eq!(Op::Push(12).arity(), 1);
         assert_eq!(Op::Call(0xFF).arity(), 1);
     }
+
+    #[test]
+    fn test_cycles() {
+        assert_eq!(Op::Add.cycles(), 1);
+        assert_eq!(Op::Push(12).cycles(), 2);
+        assert_eq!(Op::Write(4).cycles(), 6);
+    }
 }
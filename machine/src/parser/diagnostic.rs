@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use TokenType as T;
+use crate::Op;
+use super::parse_error::{render_snippet, ParseError, SourceLookup};
+use super::token::{Span, Token, TokenType};
+
+/// Problems that do not prevent the program from being assembled.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Tsify)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi, namespace)]
+pub enum ParseWarning {
+    /// The label is never used by any instruction.
+    UnusedLabel { name: String, span: Span },
+
+    /// The instruction can never be reached, e.g. it comes right after a `halt`.
+    UnreachableCode { after: String, span: Span },
+}
+
+impl ParseWarning {
+    /// Location of the warning in the source code.
+    pub fn span(&self) -> &Span {
+        match self {
+            ParseWarning::UnusedLabel { span, .. } | ParseWarning::UnreachableCode { span, .. } => span,
+        }
+    }
+
+    /// Render the warning with a caret-annotated snippet of the source code.
+    pub fn render<S: SourceLookup + ?Sized>(&self, source: &S) -> String {
+        format!("warning: {}\n{}", self, render_snippet(source, self.span()))
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::UnusedLabel { name, .. } => write!(f, "label '{}' is never used", name),
+            ParseWarning::UnreachableCode { after, .. } => write!(f, "unreachable code after '{}'", after),
+        }
+    }
+}
+
+/// Every error and warning found in a single pass of the parser.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Diagnostics {
+    pub errors: Vec<ParseError>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl Diagnostics {
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Render the errors, followed by the warnings.
+    pub fn render<S: SourceLookup + ?Sized>(&self, source: &S) -> String {
+        let errors = self.errors.iter().map(|e| e.render(source));
+        let warnings = self.warnings.iter().map(|w| w.render(source));
+
+        errors.chain(warnings).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Find the unused labels and unreachable code in the token stream.
+pub fn collect_warnings(tokens: &[Token]) -> Vec<ParseWarning> {
+    let mut warnings = vec![];
+
+    // Every identifier is a potential reference to a label.
+    let references: HashSet<&str> = tokens.iter()
+        .filter(|t| t.token_type == T::Identifier)
+        .map(|t| t.lexeme.trim())
+        .collect();
+
+    // The instruction that makes the subsequent code unreachable.
+    let mut exit: Option<String> = None;
+
+    for token in tokens {
+        match token.token_type {
+            T::LabelDefinition => {
+                // Jumping to the label makes the code reachable again.
+                exit = None;
+
+                let name = token.lexeme.trim().trim_end_matches(':');
+
+                // Local labels in macros may be unused in some of the expansions,
+                // and libraries may be included for only some of their labels.
+                let is_local = token.expanded_from.is_some() || token.file.is_some();
+
+                if !is_local && !references.contains(name) {
+                    warnings.push(ParseWarning::UnusedLabel { name: name.into(), span: token.span() });
+                }
+            }
+
+            T::Instruction => {
+                if let Some(after) = exit.take() {
+                    warnings.push(ParseWarning::UnreachableCode { after, span: token.span() });
+                }
+
+                let name = token.lexeme.trim();
+
+                if let Ok(Op::Halt | Op::Jump(..) | Op::Return) = Op::from_str(name) {
+                    exit = Some(name.into());
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    warnings
+}
@@ -0,0 +1,249 @@
+use snafu::ensure;
+use TokenType as T;
+use crate::ParseError::{ExpressionOverflow, InvalidArgToken, NoValue};
+use super::parse_error::{DivisionByZeroSnafu, InvalidArgTokenSnafu, MissingOperandSnafu, MutableValueSnafu, NoAddressSnafu, ParseError, UnclosedParenthesisSnafu, UndefinedSymbolsSnafu, ValueOverflowSnafu};
+use super::token::{Token, TokenType};
+use super::relocation::RelocationTarget;
+use super::Parser;
+
+/// Binary operators, from the lowest to the highest precedence.
+const PRECEDENCE: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"]];
+
+/// The expression being evaluated, which must fit in the operand of the instruction.
+struct Operand<'a> {
+    /// First token of the expression. The expression must be on the same line.
+    start: &'a Token,
+
+    /// Largest value of the operand, such as 0xFFFF for a word.
+    max: u32,
+}
+
+impl Parser {
+    /// Evaluate the constant expression at the current token, such as `BUF + 4` or `1 << 3`.
+    /// The expression must be on the same line. The current token is left at its last token.
+    ///
+    /// A minus sign with a space before it but not after it starts the next operand,
+    /// so `push32 0 -1` has two operands, while `0 - 1`, `0-1` and `(0 -1)` are subtractions.
+    pub(super) fn expression(&mut self) -> Result<u16, ParseError> {
+        let start = self.peek()?.clone();
+        let value = self.binary(0, &Operand { start: &start, max: u16::MAX.into() }, false)?;
+
+        Ok(value as u16)
+    }
+
+    /// Evaluate the operand of the instruction as one 32-bit value, such as `push32 100000`.
+    /// Returns None and leaves the current token as-is when it is not the only operand on the line,
+    /// such as the high and low words in `push32 1 0x86A0`.
+    pub(super) fn double_word_operand(&mut self, instruction: &Token) -> Option<u32> {
+        let (current, references) = (self.current, self.references.clone());
+
+        let start = self.next_on_line(instruction)?.clone();
+        self.current += 1;
+
+        let value = self.binary(0, &Operand { start: &start, max: u32::MAX }, false).ok();
+
+        if value.is_some() && self.next_on_line(instruction).is_none() {
+            return value;
+        }
+
+        self.current = current;
+        self.references = references;
+        None
+    }
+
+    /// Nested expressions are in parentheses, where a minus sign is always an operator.
+    fn binary(&mut self, level: usize, operand: &Operand, nested: bool) -> Result<u32, ParseError> {
+        let Some(operators) = PRECEDENCE.get(level) else {
+            return self.unary(operand);
+        };
+
+        let mut left = self.binary(level + 1, operand, nested)?;
+
+        while let Some(op) = self.next_on_line(operand.start).filter(|t| is_operator(t, operators) && (nested || !self.is_negative_operand(t))).cloned() {
+            self.current += 1;
+            self.operand(&op, operand.start)?;
+
+            let right = self.binary(level + 1, operand, nested)?;
+            left = self.apply(&op, left, right, operand.max)?;
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self, operand: &Operand) -> Result<u32, ParseError> {
+        let token = self.peek()?.clone();
+
+        if is_operator(&token, &["~"]) {
+            self.operand(&token, operand.start)?;
+
+            return Ok(!self.unary(operand)? & operand.max);
+        }
+
+        // Negative numbers are stored as two's complement, such as `-1` as 0xFFFF.
+        if is_operator(&token, &["-"]) {
+            self.operand(&token, operand.start)?;
+
+            return Ok(self.unary(operand)?.wrapping_neg() & operand.max);
+        }
+
+        // Address-of and value-of the symbol, such as `&counter` and `*counter`
+        if is_operator(&token, &["&", "*"]) {
+            self.operand(&token, operand.start)?;
+
+            let symbol = self.peek()?.clone();
+            ensure!(symbol.token_type == T::Identifier, InvalidArgTokenSnafu { span: symbol.span() });
+
+            let value = match token.lexeme.trim() {
+                "&" => self.address_of(&symbol),
+                _ => self.value_of(&symbol),
+            };
+
+            return value.map(u32::from);
+        }
+
+        self.primary(&token, operand)
+    }
+
+    /// Returns the address of the label or data, regardless of how the symbol is defined.
+    fn address_of(&mut self, token: &Token) -> Result<u16, ParseError> {
+        let key = token.lexeme.trim();
+
+        // Constants are never stored in memory.
+        ensure!(!self.symbols.constants.contains_key(key), NoAddressSnafu { span: token.span() });
+
+        // Return a placeholder for the scanning phase.
+        if !self.symbol_scanned { return Ok(0x00); }
+
+        let Some(offset) = self.symbols.offsets.get(key).copied() else {
+            // Imported symbols are resolved by the linker.
+            ensure!(self.imports.iter().any(|name| name == key), UndefinedSymbolsSnafu { span: token.span() });
+
+            self.references.push(RelocationTarget::Import(key.into()));
+            return Ok(0x00);
+        };
+
+        // Labels are offsets within the code segment.
+        if !self.symbols.is_data(key) {
+            self.references.push(RelocationTarget::Code);
+            return Ok(offset);
+        }
+
+        self.references.push(RelocationTarget::Data);
+        Ok(self.layout.data_start() + offset)
+    }
+
+    /// Returns the value of the constant, or the first word stored in the data.
+    /// The value is known when the program is assembled, so it is not read from memory at runtime.
+    /// Variables change at runtime, so they are read with `load NAME` instead.
+    fn value_of(&self, token: &Token) -> Result<u16, ParseError> {
+        let key = token.lexeme.trim();
+
+        if let Some(value) = self.symbols.constants.get(key) {
+            return Ok(*value);
+        }
+
+        // Return a placeholder for the scanning phase.
+        if !self.symbol_scanned { return Ok(0x00); }
+
+        ensure!(self.symbols.offsets.contains_key(key), UndefinedSymbolsSnafu { span: token.span() });
+        ensure!(!self.symbols.variables.contains(key), MutableValueSnafu { span: token.span() });
+
+        let symbols = &self.symbols;
+
+        let value = match symbols.strings.get(key) {
+            Some(text) => text.encode_utf16().next(),
+            None => symbols.data.get(key).or(symbols.words.get(key)).and_then(|words| words.first().copied()),
+        };
+
+        value.ok_or(NoValue { span: token.span() })
+    }
+
+    fn primary(&mut self, token: &Token, operand: &Operand) -> Result<u32, ParseError> {
+        match token.token_type {
+            // Values larger than a word are only scanned for the 32-bit operands.
+            T::Value(value) => {
+                ensure!(value <= operand.max, ValueOverflowSnafu { span: token.span() });
+
+                Ok(value)
+            }
+
+            T::Identifier => self.op_arg(token).map(u32::from),
+
+            T::Operator if is_operator(token, &["("]) => {
+                self.operand(token, operand.start)?;
+                let value = self.binary(0, operand, true)?;
+
+                // The closing parenthesis.
+                let closed = self.next_on_line(operand.start).is_some_and(|t| is_operator(t, &[")"]));
+                ensure!(closed, UnclosedParenthesisSnafu { span: token.span() });
+                self.current += 1;
+
+                Ok(value)
+            }
+
+            _ => Err(InvalidArgToken { span: token.span() }),
+        }
+    }
+
+    /// Move from the operator to its operand, which must be on the same line.
+    fn operand(&mut self, op: &Token, start: &Token) -> Result<(), ParseError> {
+        ensure!(self.next_on_line(start).is_some(), MissingOperandSnafu { span: op.span() });
+        self.current += 1;
+
+        Ok(())
+    }
+
+    /// Is the minus sign the start of a negative operand, such as the `-1` in `0 -1`?
+    fn is_negative_operand(&self, op: &Token) -> bool {
+        let (Some(prev), Some(next)) = (self.tokens.get(self.current), self.tokens.get(self.current + 2)) else {
+            return false;
+        };
+
+        is_operator(op, &["-"]) && is_spaced(prev, op) && !is_spaced(op, next) && next.same_line(op)
+    }
+
+    /// Returns the next token if it is on the same line as the expression.
+    pub(super) fn next_on_line(&self, start: &Token) -> Option<&Token> {
+        self.tokens.get(self.current + 1)
+            .filter(|t| t.same_line(start) && t.expanded_from == start.expanded_from)
+    }
+
+    fn apply(&self, op: &Token, left: u32, right: u32, max: u32) -> Result<u32, ParseError> {
+        let symbol = op.lexeme.trim();
+
+        let result = match symbol {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            "&" => Some(left & right),
+            "|" => Some(left | right),
+            "^" => Some(left ^ right),
+
+            // Shifting out any of the set bits is an overflow.
+            "<<" => left.checked_shl(right).filter(|v| v >> right == left),
+            ">>" => Some(left.checked_shr(right).unwrap_or(0)),
+
+            _ => None,
+        };
+
+        let result = result.filter(|v| *v <= max);
+
+        // Symbols are placeholders in the first pass, so the result is not meaningful yet.
+        if !self.symbol_scanned { return Ok(result.unwrap_or(0)); }
+
+        ensure!(!(matches!(symbol, "/" | "%") && right == 0), DivisionByZeroSnafu { span: op.span() });
+
+        result.ok_or(ExpressionOverflow { left, right, span: op.span() })
+    }
+}
+
+fn is_operator(token: &Token, operators: &[&str]) -> bool {
+    token.token_type == T::Operator && operators.contains(&token.lexeme.trim())
+}
+
+/// Is there whitespace between the tokens?
+pub(super) fn is_spaced(prev: &Token, next: &Token) -> bool {
+    next.column > prev.column + prev.lexeme.trim_end().chars().count()
+}
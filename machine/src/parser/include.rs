@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use TokenType as T;
+use crate::ParseError::{IncludeCycle, IncludeNotFound, InvalidInclude};
+use super::parse_error::ParseError;
+use super::resolver::FileResolver;
+use super::scanner::Scanner;
+use super::token::{Token, TokenType};
+
+/// Replaces the `.include "path"` directives with the tokens of the included files.
+pub struct IncludeExpander<'a> {
+    resolver: &'a dyn FileResolver,
+
+    /// Name of the main source file. Includes in the main source code are relative to it.
+    main: Option<String>,
+
+    /// Source code of every included file, keyed by the resolved file name.
+    pub sources: HashMap<String, String>,
+
+    /// Errors found in the include directives and the included files.
+    pub errors: Vec<ParseError>,
+
+    /// Files being included, used to detect include cycles.
+    stack: Vec<String>,
+}
+
+impl<'a> IncludeExpander<'a> {
+    /// `file` is the name of the main source file, if it has one.
+    pub fn new(resolver: &'a dyn FileResolver, file: Option<&str>) -> IncludeExpander<'a> {
+        let main = file.map(|f| resolver.resolve(f, None));
+
+        IncludeExpander {
+            resolver,
+            stack: main.iter().cloned().collect(),
+            main,
+            sources: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Expand every include directive. Returns the first error found.
+    pub fn expand(&mut self, tokens: Vec<Token>) -> Result<Vec<Token>, ParseError> {
+        let tokens = self.expand_with_recovery(tokens);
+
+        match self.errors.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(tokens),
+        }
+    }
+
+    /// Expand every include directive, skipping the invalid ones.
+    /// The errors are collected into `errors`.
+    pub fn expand_with_recovery(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut output = vec![];
+        let mut tokens = tokens.into_iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            if token.token_type != T::Include {
+                output.push(token);
+                continue;
+            }
+
+            // The path is a string on the same line as the directive.
+            let path = tokens.next_if(|t| t.same_line(&token) && matches!(t.token_type, T::String(..)));
+
+            let Some(Token { token_type: T::String(path), .. }) = path else {
+                self.errors.push(InvalidInclude { span: token.span() });
+                continue;
+            };
+
+            output.extend(self.include(&token, &path));
+        }
+
+        output
+    }
+
+    /// Scan the included file and expand its own include directives.
+    fn include(&mut self, directive: &Token, path: &str) -> Vec<Token> {
+        let from = directive.file.as_ref().or(self.main.as_ref());
+        let file = self.resolver.resolve(path, from.map(|f| f.as_str()));
+
+        // The file is already being included further up.
+        if self.stack.contains(&file) {
+            self.errors.push(IncludeCycle { path: file, span: directive.span() });
+            return vec![];
+        }
+
+        let Some(source) = self.resolver.read(&file) else {
+            self.errors.push(IncludeNotFound { path: file, span: directive.span() });
+            return vec![];
+        };
+
+        let mut scanner = Scanner::new(&source).in_file(&file);
+        self.errors.extend(scanner.scan_tokens_with_recovery());
+        self.sources.insert(file.clone(), source);
+
+        self.stack.push(file);
+        let tokens = self.expand_with_recovery(scanner.tokens);
+        self.stack.pop();
+
+        tokens
+    }
+}
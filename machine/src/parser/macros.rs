@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec::IntoIter;
+use snafu::ensure;
+use TokenType as T;
+use crate::Op;
+use crate::ParseError::{DuplicateMacroDefinition, InvalidMacroDefinition, MacroArgumentMismatch, MacroExpansionFailed, MacroRecursionLimit, NestedMacroDefinition, UnexpectedMacroEnd};
+use super::parse_error::{InvalidMacroNameSnafu, ParseError, UnterminatedMacroSnafu};
+use super::expr::is_spaced;
+use super::token::{Span, Token, TokenType};
+
+/// How deep can macros expand into other macros?
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// Call site of a macro expansion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroCall {
+    /// Name of the macro being expanded.
+    pub name: String,
+
+    /// Location of the call site.
+    pub span: Span,
+}
+
+/// Macro defined with `.macro NAME arg1 arg2 ... .endmacro`
+#[derive(Clone, Debug)]
+pub struct Macro {
+    pub name: String,
+
+    /// Names of the parameters, substituted by the arguments on expansion.
+    pub params: Vec<String>,
+
+    /// Tokens between the macro header and `.endmacro`.
+    pub body: Vec<Token>,
+
+    /// Location of the macro name in the definition.
+    pub span: Span,
+}
+
+/// Expands macro definitions and calls in the token stream.
+#[derive(Clone, Debug, Default)]
+pub struct MacroExpander {
+    pub macros: HashMap<String, Macro>,
+
+    /// Errors found in the macro definitions and calls.
+    pub errors: Vec<ParseError>,
+
+    /// Number of expansions so far. Used to generate unique local labels.
+    expansions: usize,
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+impl MacroExpander {
+    pub fn new() -> MacroExpander {
+        MacroExpander { macros: HashMap::new(), errors: vec![], expansions: 0 }
+    }
+
+    /// Collect the macro definitions, then expand every macro call.
+    /// Returns the first error found.
+    pub fn expand(&mut self, tokens: Vec<Token>) -> Result<Vec<Token>, ParseError> {
+        let tokens = self.expand_with_recovery(tokens);
+
+        match self.errors.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(tokens),
+        }
+    }
+
+    /// Expand every macro call, skipping the invalid definitions and calls.
+    /// The errors are collected into `errors`.
+    pub fn expand_with_recovery(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        let tokens = self.collect(tokens);
+
+        self.expand_tokens(tokens, 0)
+    }
+
+    /// Remove the macro definitions from the token stream and store them.
+    fn collect(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut output = vec![];
+        let mut tokens = tokens.into_iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            match token.token_type {
+                T::MacroDefinition => match self.definition(&token, &mut tokens) {
+                    Ok(m) => self.define(m),
+                    Err(error) => self.errors.push(error),
+                },
+
+                T::MacroEnd => self.errors.push(UnexpectedMacroEnd { span: token.span() }),
+
+                _ => output.push(token),
+            }
+        }
+
+        output
+    }
+
+    fn define(&mut self, m: Macro) {
+        // The same macro is defined twice.
+        if self.macros.contains_key(&m.name) {
+            self.errors.push(DuplicateMacroDefinition { name: m.name, span: m.span });
+            return;
+        }
+
+        self.macros.insert(m.name.clone(), m);
+    }
+
+    /// Parse the macro definition.
+    /// The whole definition is consumed even if it is invalid, so we can skip over it.
+    fn definition(&self, keyword: &Token, tokens: &mut Tokens) -> Result<Macro, ParseError> {
+        // The macro name and parameters are on the same line as the `.macro` keyword.
+        let mut header = vec![];
+
+        while let Some(token) = tokens.next_if(|t| t.same_line(keyword) && t.token_type == T::Identifier) {
+            header.push(token);
+        }
+
+        let mut body = vec![];
+        let mut nested: Option<Span> = None;
+        let mut terminated = false;
+
+        for token in tokens.by_ref() {
+            match token.token_type {
+                T::MacroEnd => {
+                    terminated = true;
+                    break;
+                }
+
+                T::MacroDefinition => { nested.get_or_insert(token.span()); }
+                _ => body.push(token),
+            }
+        }
+
+        let Some((name_token, params)) = header.split_first() else {
+            return Err(InvalidMacroDefinition { span: keyword.span() });
+        };
+
+        let name = name_token.lexeme.trim().to_owned();
+
+        if let Some(span) = nested {
+            return Err(NestedMacroDefinition { name, span });
+        }
+
+        ensure!(terminated, UnterminatedMacroSnafu { name, span: keyword.span() });
+
+        // Macros cannot shadow the built-in instructions.
+        ensure!(Op::from_str(&name).is_err(), InvalidMacroNameSnafu { name, span: name_token.span() });
+
+        Ok(Macro {
+            name,
+            params: params.iter().map(|p| p.lexeme.trim().to_owned()).collect(),
+            body,
+            span: name_token.span(),
+        })
+    }
+
+    fn expand_tokens(&mut self, tokens: Vec<Token>, depth: usize) -> Vec<Token> {
+        let mut output = vec![];
+        let mut tokens = tokens.into_iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            let Some(m) = self.macro_of(&token).cloned() else {
+                output.push(token);
+                continue;
+            };
+
+            // Arguments are on the same line as the macro call.
+            let mut line = vec![];
+
+            while let Some(arg) = tokens.next_if(|t| t.same_line(&token) && is_argument(t)) {
+                line.push(arg);
+            }
+
+            let args = split_arguments(line);
+
+            if depth >= MAX_EXPANSION_DEPTH {
+                self.errors.push(MacroRecursionLimit { name: m.name, span: token.span() });
+                continue;
+            }
+
+            if args.len() != m.params.len() {
+                self.errors.push(MacroArgumentMismatch {
+                    name: m.name,
+                    expected: m.params.len(),
+                    given: args.len(),
+                    span: token.span(),
+                });
+
+                continue;
+            }
+
+            let body = self.substitute(&m, &token, &args);
+            let expanded = self.expand_tokens(body, depth + 1);
+            output.extend(expanded);
+        }
+
+        output
+    }
+
+    /// Returns the macro if the token is a macro call.
+    fn macro_of(&self, token: &Token) -> Option<&Macro> {
+        if token.token_type != T::Instruction { return None; }
+
+        self.macros.get(token.lexeme.trim())
+    }
+
+    /// Replace the parameters with arguments, and give the local labels unique names.
+    /// The arguments are substituted as written, so `push a * 2` with `1 + 2` as `a` is `push 1 + 2 * 2`.
+    fn substitute(&mut self, m: &Macro, call: &Token, args: &[Vec<Token>]) -> Vec<Token> {
+        self.expansions += 1;
+
+        let id = self.expansions;
+        let local_name = |label: &str| format!("__{}_{}_{}", m.name, id, label);
+
+        // Labels defined within the macro body are local to each expansion.
+        let labels: Vec<String> = m.body.iter()
+            .filter(|t| t.token_type == T::LabelDefinition)
+            .filter_map(|t| t.lexeme.trim().strip_suffix(':').map(|l| l.to_owned()))
+            .collect();
+
+        let expanded_from = Some(MacroCall { name: m.name.clone(), span: call.span() });
+
+        m.body.iter().flat_map(|token| {
+            let mut token = token.clone();
+            let key = token.lexeme.trim().to_owned();
+            token.expanded_from = expanded_from.clone();
+
+            match token.token_type {
+                T::Identifier => {
+                    if let Some(index) = m.params.iter().position(|p| *p == key) {
+                        return place_argument(&args[index], &token);
+                    }
+
+                    if labels.contains(&key) {
+                        token.lexeme = local_name(&key);
+                    }
+                }
+
+                T::LabelDefinition => {
+                    if let Some(label) = key.strip_suffix(':') {
+                        token.lexeme = format!("{}:", local_name(label));
+                    }
+                }
+
+                _ => {}
+            }
+
+            vec![token]
+        }).collect()
+    }
+}
+
+/// Move the argument to where the parameter is in the macro body.
+/// The tokens keep their spacing, so the expression parser reads them the same as at the call site.
+fn place_argument(arg: &[Token], param: &Token) -> Vec<Token> {
+    let start = arg.first().map_or(0, |t| t.column);
+
+    arg.iter().map(|t| Token {
+        line: param.line,
+        column: param.column + t.column - start,
+        file: param.file.clone(),
+        expanded_from: param.expanded_from.clone(),
+        ..t.clone()
+    }).collect()
+}
+
+/// Split the tokens after the macro call into arguments.
+/// Each argument is an operand, such as `5`, `-1`, `&counter`, `(1 + 2)` or `BUF + 4`.
+/// An operand right after a complete operand starts the next argument, so `push_sum 10 20` has two arguments.
+/// A minus sign is spaced the same as in `push32 5 -1`, so `10 -20` is two arguments and `10 - 20` is one.
+fn split_arguments(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut args: Vec<Vec<Token>> = vec![];
+    let mut depth = 0usize;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let symbol = token.lexeme.trim();
+        let is_operator = token.token_type == T::Operator;
+
+        let starts_argument = match i.checked_sub(1).map(|i| &tokens[i]) {
+            None => true,
+            Some(prev) if depth > 0 || !ends_operand(prev) => false,
+            Some(_) if !is_operator => true,
+            Some(prev) => match symbol {
+                "(" | "~" => true,
+                "-" => is_spaced(prev, token) && tokens.get(i + 1).is_some_and(|next| !is_spaced(token, next)),
+                _ => false,
+            },
+        };
+
+        if starts_argument {
+            args.push(vec![]);
+        }
+
+        if is_operator {
+            match symbol {
+                "(" => depth += 1,
+                ")" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        if let Some(arg) = args.last_mut() {
+            arg.push(token.clone());
+        }
+    }
+
+    args
+}
+
+/// Does the token end an operand, such as `5`, `name` or `)`?
+fn ends_operand(token: &Token) -> bool {
+    token.token_type != T::Operator || token.lexeme.trim() == ")"
+}
+
+fn is_argument(token: &Token) -> bool {
+    matches!(token.token_type, T::Identifier | T::Value(..) | T::String(..) | T::Operator)
+}
+
+/// Attach the macro call site to errors raised from the expanded tokens.
+pub fn with_expansion(token: &Token, error: ParseError) -> ParseError {
+    let Some(call) = &token.expanded_from else { return error; };
+
+    MacroExpansionFailed {
+        name: call.name.clone(),
+        error: Box::new(error),
+        span: call.span.clone(),
+    }
+}
@@ -2,17 +2,30 @@ pub mod token;
 pub mod scanner;
 pub mod symbols;
 pub mod parse_error;
+pub mod macros;
+pub mod diagnostic;
+pub mod resolver;
+pub mod include;
+pub mod expr;
+pub mod relocation;
 
 pub use token::*;
 pub use scanner::*;
 pub use symbols::*;
 pub use parse_error::*;
-
+pub use macros::*;
+pub use diagnostic::*;
+pub use resolver::*;
+pub use include::*;
+pub use relocation::*;
+
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::str::FromStr;
 use snafu::ensure;
 use TokenType as T;
-use crate::{DATA_START, Op};
-use crate::ParseError::{CannotPeekAtToken, InvalidArgToken, InvalidByteValue, InvalidIdentifier, InvalidLabelDescription, InvalidStringValue, UndefinedInstruction, UndefinedSymbols};
+use crate::{LayoutError, MemoryLayout, Op};
+use crate::ParseError::{CannotPeekAtToken, DataSegmentOverflow, EmptyProgram, ExtraOperand, InvalidArgToken, InvalidByteValue, InvalidConstantDefinition, InvalidEntry, InvalidIdentifier, InvalidLabelDescription, InvalidStringValue, UndefinedInstruction, UndefinedSymbols};
 
 type Errorable = Result<(), ParseError>;
 
@@ -27,6 +40,9 @@ pub struct Parser {
     /// Output a set of operations.
     pub ops: Vec<Op>,
 
+    /// Location of each operation in the source code.
+    pub spans: Vec<Span>,
+
     /// Output a set of symbols.
     pub symbols: Symbols,
 
@@ -41,6 +57,42 @@ pub struct Parser {
 
     /// Current data offsets
     data_offset: u16,
+
+    /// Should we skip to the next line on errors, instead of stopping?
+    recover: bool,
+
+    /// Errors collected while recovering.
+    errors: Vec<ParseError>,
+
+    /// Locates the files referenced by `.include`.
+    resolver: Rc<dyn FileResolver>,
+
+    /// Name of the main source file, if it has one.
+    file: Option<String>,
+
+    /// Source code of the included files, keyed by their file name.
+    pub sources: HashMap<String, String>,
+
+    /// Symbols that other files can link against, declared with `.export`.
+    pub exports: Vec<String>,
+
+    /// Symbols defined in other files, declared with `.import`.
+    pub imports: Vec<String>,
+
+    /// Label to start running from, declared with `.entry`.
+    pub entry: Option<String>,
+
+    /// Words that hold addresses, which the linker adjusts when the file is linked.
+    pub relocations: Vec<Relocation>,
+
+    /// Address symbols referenced by the expression being evaluated.
+    references: Vec<RelocationTarget>,
+
+    /// Address symbols referenced by the data values being defined, by their index.
+    data_references: Vec<(u16, RelocationTarget)>,
+
+    /// Memory layout of the machine that runs the program, which decides where the code and data are.
+    pub layout: MemoryLayout,
 }
 
 impl Parser {
@@ -49,23 +101,66 @@ impl Parser {
             source: source.into(),
             tokens: vec![],
             ops: vec![],
+            spans: vec![],
             symbols: Symbols::new(),
             symbol_scanned: false,
 
             current: 0,
             code_offset: 0,
             data_offset: 0,
+
+            recover: false,
+            errors: vec![],
+
+            resolver: Rc::new(MemoryResolver::default()),
+            file: None,
+            sources: HashMap::new(),
+
+            exports: vec![],
+            imports: vec![],
+            entry: None,
+            relocations: vec![],
+            references: vec![],
+            data_references: vec![],
+            layout: MemoryLayout::default(),
         }
     }
 
+    /// Use the resolver to read the included files.
+    pub fn with_resolver(mut self, resolver: impl FileResolver + 'static) -> Parser {
+        self.resolver = Rc::new(resolver);
+        self
+    }
+
+    /// Assemble the program for the memory layout of the machine.
+    pub fn with_layout(mut self, layout: MemoryLayout) -> Result<Parser, LayoutError> {
+        layout.validate()?;
+        self.layout = layout;
+
+        Ok(self)
+    }
+
+    /// Set the name of the main source file. Includes are resolved relative to it.
+    pub fn with_file(mut self, file: &str) -> Parser {
+        self.file = Some(file.into());
+        self
+    }
+
     pub fn parse(&mut self) -> Errorable {
         // Scan tokens from the source code.
         let mut scanner = Scanner::new(&self.source);
         scanner.scan_tokens()?;
-        self.tokens = scanner.tokens;
+
+        // Replace the include directives with the tokens of the included files.
+        let mut includes = IncludeExpander::new(&*self.resolver, self.file.as_deref());
+        let tokens = includes.expand(scanner.tokens)?;
+        self.sources = includes.sources;
+
+        // Expand the macros into tokens.
+        self.tokens = MacroExpander::new().expand(tokens)?;
 
         // Raise an error if the program is empty.
-        ensure!(!self.tokens.is_empty(), EmptyProgramSnafu);
+        ensure!(!self.tokens.is_empty(), EmptyProgramSnafu { span: Span::default() });
 
         // Pass 1: collect labels.
         self.parse_tokens()?;
@@ -76,17 +171,71 @@ impl Parser {
         Ok(())
     }
 
+    /// Parse the source code, collecting every error and warning instead of stopping at the first error.
+    pub fn parse_with_recovery(&mut self) -> Diagnostics {
+        self.recover = true;
+        self.errors.clear();
+
+        // Scan tokens from the source code, skipping the lines with errors.
+        let mut scanner = Scanner::new(&self.source);
+        self.errors.extend(scanner.scan_tokens_with_recovery());
+
+        // Include the files, skipping the missing and cyclic includes.
+        let mut includes = IncludeExpander::new(&*self.resolver, self.file.as_deref());
+        let tokens = includes.expand_with_recovery(scanner.tokens);
+        self.errors.extend(includes.errors);
+        self.sources = includes.sources;
+
+        // Expand the macros into tokens, skipping the invalid definitions and calls.
+        let mut expander = MacroExpander::new();
+        self.tokens = expander.expand_with_recovery(tokens);
+        self.errors.extend(expander.errors);
+
+        if self.tokens.is_empty() && self.errors.is_empty() {
+            self.errors.push(EmptyProgram { span: Span::default() });
+        }
+
+        // Errors are collected by the parser instead of being returned.
+        let _ = self.parse_tokens();
+        let _ = self.parse_tokens();
+
+        // Each pass finds different errors, so we report them in the source order.
+        self.errors.sort_by_key(|e| (e.span().file.clone(), e.span().line, e.span().column));
+
+        Diagnostics {
+            errors: self.errors.clone(),
+            warnings: collect_warnings(&self.tokens),
+        }
+    }
+
     pub fn parse_tokens(&mut self) -> Errorable {
         // Reset the parser state.
         self.current = 0;
         self.code_offset = 0;
         self.data_offset = 0;
         self.ops.clear();
+        self.spans.clear();
+        self.relocations.clear();
+
+        // Constants are defined again in each pass, so they can only be used after their definition.
+        self.symbols.constants.clear();
 
         // Parse each token.
         while self.current < self.tokens.len() {
-            let token = self.peek()?;
-            self.parse_token(&token.clone())?;
+            let token = self.peek()?.clone();
+            let start = self.current;
+
+            if let Err(error) = self.parse_token(&token) {
+                let error = with_expansion(&token, error);
+                if !self.recover { return Err(error); }
+
+                // The same error may be found in both passes.
+                if !self.errors.contains(&error) {
+                    self.errors.push(error);
+                }
+
+                self.skip_line(start);
+            }
         }
 
         // Mark symbol scanning phase to be completed.
@@ -101,12 +250,25 @@ impl Parser {
         match token.token_type {
             T::LabelDefinition => self.save_label(token)?,
             T::Instruction => self.save_instruction(token)?,
-            T::StringDefinition => self.save_string()?,
-            T::ValueDefinition => self.save_value()?,
+            T::StringDefinition => self.save_string(token)?,
+            T::ValueDefinition => self.save_value(token)?,
+            T::ConstantDefinition => self.save_constant(token)?,
+            T::VariableDefinition => self.save_variable(token)?,
+            T::WordsDefinition => self.save_words(token)?,
+            T::BytesDefinition => self.save_bytes(token)?,
+            T::ReserveDefinition => self.save_reserve(token)?,
+            T::Align => self.save_align(token)?,
+            T::Export => self.save_export(token)?,
+            T::Import => self.save_import(token)?,
+            T::Entry => self.save_entry(token)?,
             T::Identifier => {}
             T::String(..) => {}
             T::Value(..) => {}
+            T::Operator => {}
             T::Eof => {}
+
+            // Macros and includes are expanded before parsing.
+            T::MacroDefinition | T::MacroEnd | T::Include => {}
         }
 
         self.current += 1;
@@ -114,6 +276,18 @@ impl Parser {
         Ok(())
     }
 
+    /// Skip to the first token after the line of the given token.
+    fn skip_line(&mut self, start: usize) {
+        let token = &self.tokens[start];
+        self.current = start + 1;
+
+        while let Some(next) = self.tokens.get(self.current) {
+            if !next.same_line(token) || next.expanded_from != token.expanded_from { break; }
+
+            self.current += 1;
+        }
+    }
+
     fn advance(&mut self) {
         if self.current >= self.tokens.len() - 1 {
             println!("cannot advance! {} >= {}", self.current, self.tokens.len());
@@ -128,10 +302,11 @@ impl Parser {
         if self.symbol_scanned { return Ok(()); }
 
         let key = token.lexeme.clone();
-        let key = key.trim().strip_suffix(":").ok_or(InvalidLabelDescription)?;
+        let key = key.trim().strip_suffix(":").ok_or(InvalidLabelDescription { span: token.span() })?;
 
         // Raise an error if the label was defined before.
-        ensure!(!self.symbols.offsets.contains_key(key), DuplicateLabelDefinitionSnafu);
+        let is_defined = self.symbols.offsets.contains_key(key) || self.symbols.constants.contains_key(key);
+        ensure!(!is_defined, DuplicateLabelDefinitionSnafu { span: token.span() });
 
         // Define labels based on the token.
         let offset = self.code_offset;
@@ -145,12 +320,16 @@ impl Parser {
 
         match token.token_type {
             TokenType::Identifier => Ok(token.lexeme.clone()),
-            _ => Err(InvalidIdentifier),
+            _ => Err(InvalidIdentifier { span: token.span() }),
         }
     }
 
     fn peek(&self) -> Result<&Token, ParseError> {
-        self.tokens.get(self.current).ok_or(CannotPeekAtToken)
+        self.tokens.get(self.current).ok_or_else(|| {
+            let span = self.tokens.last().map(|t| t.span()).unwrap_or_default();
+
+            CannotPeekAtToken { span }
+        })
     }
 
     fn string_value(&self) -> Result<String, ParseError> {
@@ -158,7 +337,7 @@ impl Parser {
 
         match &token.token_type {
             TokenType::String(value) => Ok(value.into()),
-            _ => Err(InvalidStringValue),
+            _ => Err(InvalidStringValue { span: token.span() }),
         }
     }
 
@@ -166,8 +345,8 @@ impl Parser {
         let token = self.peek()?;
 
         match token.token_type {
-            TokenType::Value(value) => Ok(value),
-            _ => Err(InvalidByteValue),
+            TokenType::Value(value) => u16::try_from(value).map_err(|_| InvalidByteValue { span: token.span() }),
+            _ => Err(InvalidByteValue { span: token.span() }),
         }
     }
 
@@ -179,110 +358,434 @@ impl Parser {
         let key = self.identifier_name()?;
 
         // The same symbol is defined twice.
-        ensure!(!self.symbols.offsets.contains_key(&key), DuplicateSymbolDefinitionSnafu);
+        let is_defined = self.symbols.offsets.contains_key(&key) || self.symbols.constants.contains_key(&key);
+        ensure!(!is_defined, DuplicateSymbolDefinitionSnafu { span: self.peek()?.span() });
 
         self.symbols.offsets.insert(key.clone(), self.data_offset);
 
         self.advance();
-        self.data_offset += 1;
 
         Ok(Some(key))
     }
 
-    fn save_string(&mut self) -> Errorable {
-        let Some(key) = self.symbol().map_err(|_| InvalidIdentifier)? else {
+    fn save_string(&mut self, token: &Token) -> Errorable {
+        let Some(key) = self.symbol()? else {
             return Ok(());
         };
 
         // The same string is defined twice.
-        ensure!(!self.symbols.strings.contains_key(&key), DuplicateStringDefinitionSnafu);
+        ensure!(!self.symbols.strings.contains_key(&key), DuplicateStringDefinitionSnafu { span: self.peek()?.span() });
 
         let value = self.string_value()?;
-        let len = value.len() as u16;
-        self.data_offset += len;
+
+        // Strings are stored as UTF-16, and terminated with a null character.
+        self.advance_data_offset(value.encode_utf16().count() + 1, token)?;
 
         self.symbols.strings.insert(key.clone(), value);
 
         Ok(())
     }
 
-    fn save_value(&mut self) -> Errorable {
-        let Some(key) = self.symbol().map_err(|_| InvalidIdentifier)? else {
+    fn save_value(&mut self, token: &Token) -> Errorable {
+        let Some(key) = self.symbol()? else {
             return Ok(());
         };
 
         self.symbols.data.insert(key.clone(), vec![self.byte_value()?]);
-        self.data_offset += 1;
+        self.advance_data_offset(1, token)?;
+
+        Ok(())
+    }
+
+    /// Define a mutable variable with `.var NAME value`
+    /// The variable is a data cell, so its name refers to its address, e.g. `load NAME`
+    fn save_variable(&mut self, token: &Token) -> Errorable {
+        let key = self.data_symbol(token)?;
+        let values = self.data_values(token)?;
+
+        ensure!(values.len() <= 1, InvalidVariableDefinitionSnafu { span: token.span() });
+
+        // The variable is initialized to zero if the value is omitted.
+        self.symbols.variables.insert(key.clone());
+        self.save_data(token, key, vec![values.first().copied().unwrap_or(0)])
+    }
+
+    /// Define an array with `.words NAME 1, 2, 3`
+    fn save_words(&mut self, token: &Token) -> Errorable {
+        let key = self.data_symbol(token)?;
+        let words = self.data_values(token)?;
+
+        self.save_data(token, key, words)
+    }
+
+    /// Define an array of bytes packed two per word with `.bytes NAME 1, 2, 3`
+    /// The first byte is stored in the high byte of the word.
+    fn save_bytes(&mut self, token: &Token) -> Errorable {
+        let key = self.data_symbol(token)?;
+        let bytes = self.data_values(token)?;
+
+        ensure!(bytes.iter().all(|b| *b <= 0xFF), InvalidByteValueSnafu { span: token.span() });
+
+        // Packed bytes are too small to hold the addresses.
+        self.data_references.clear();
+
+        let words = bytes.chunks(2)
+            .map(|pair| pair[0] << 8 | pair.get(1).copied().unwrap_or(0))
+            .collect();
+
+        self.save_data(token, key, words)
+    }
+
+    /// Reserve zero-filled space with `.zero NAME size` or `.reserve NAME size`
+    fn save_reserve(&mut self, token: &Token) -> Errorable {
+        let key = self.data_symbol(token)?;
+        let size = self.data_value(token)?;
+
+        // The size must be known in the first pass, so the rest of the data is laid out after it.
+        if self.symbol_scanned { return Ok(()); }
+
+        self.save_data(token, key, vec![0; size as usize])
+    }
+
+    /// Align the next data definition to a multiple of the size with `.align size`
+    fn save_align(&mut self, token: &Token) -> Errorable {
+        let size = self.data_value(token)?;
+
+        // The offsets are already laid out in the first pass.
+        if self.symbol_scanned { return Ok(()); }
+
+        ensure!(size > 0, InvalidAlignmentSnafu { span: token.span() });
+
+        let padding = (size - self.data_offset % size) % size;
+
+        self.advance_data_offset(padding as usize, token)
+    }
+
+    /// Define the name of the data at the current data offset.
+    fn data_symbol(&mut self, token: &Token) -> Result<String, ParseError> {
+        let name = self.tokens.get(self.current + 1)
+            .filter(|t| t.same_line(token) && t.token_type == T::Identifier)
+            .ok_or(InvalidIdentifier { span: token.span() })?;
+
+        let key = name.lexeme.trim().to_owned();
+        let span = name.span();
+        self.current += 1;
+
+        // The offsets are already defined in the first pass.
+        if self.symbol_scanned { return Ok(key); }
+
+        let is_defined = self.symbols.offsets.contains_key(&key) || self.symbols.constants.contains_key(&key);
+        ensure!(!is_defined, DuplicateSymbolDefinitionSnafu { span });
+
+        self.symbols.offsets.insert(key.clone(), self.data_offset);
+
+        Ok(key)
+    }
+
+    /// Evaluate the values on the same line, which may be separated by commas.
+    /// The characters of the strings are expanded into values.
+    fn data_values(&mut self, token: &Token) -> Result<Vec<u16>, ParseError> {
+        let mut values = vec![];
+        self.data_references.clear();
+
+        while let Some(next) = self.tokens.get(self.current + 1).filter(|t| t.same_line(token)).cloned() {
+            self.current += 1;
+
+            match next.token_type {
+                T::String(text) => values.extend(text.encode_utf16()),
+                _ => {
+                    self.references.clear();
+                    values.push(self.expression()?);
+
+                    if let Some(target) = self.take_reference() {
+                        self.data_references.push((values.len() as u16 - 1, target));
+                    }
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Evaluate the single value on the same line.
+    fn data_value(&mut self, token: &Token) -> Result<u16, ParseError> {
+        let has_value = self.tokens.get(self.current + 1).is_some_and(|t| t.same_line(token));
+        ensure!(has_value, InvalidByteValueSnafu { span: token.span() });
+
+        self.current += 1;
+
+        self.expression()
+    }
+
+    /// Store the data. The values are evaluated again in the second pass, once every label is known.
+    fn save_data(&mut self, token: &Token, key: String, words: Vec<u16>) -> Errorable {
+        if !self.symbol_scanned {
+            self.advance_data_offset(words.len(), token)?;
+        }
+
+        let references = std::mem::take(&mut self.data_references);
+
+        if let (true, Some(start)) = (self.symbol_scanned, self.symbols.offsets.get(&key)) {
+            for (index, target) in references {
+                self.relocations.push(Relocation { segment: Segment::Data, offset: start + index, target });
+            }
+        }
+
+        self.symbols.words.insert(key, words);
+
+        Ok(())
+    }
+
+    /// Move the data offset past the data, which must fit in the data segment.
+    fn advance_data_offset(&mut self, size: usize, token: &Token) -> Errorable {
+        let offset = u16::try_from(size).ok()
+            .and_then(|size| self.data_offset.checked_add(size))
+            .filter(|offset| *offset <= self.layout.data_size)
+            .ok_or(DataSegmentOverflow { span: token.span() })?;
+
+        self.data_offset = offset;
+
+        Ok(())
+    }
+
+    /// Define a constant with `.const NAME = expression`
+    fn save_constant(&mut self, token: &Token) -> Errorable {
+        // The name, the equal sign and the value are on the same line as the keyword.
+        let on_line = |offset: usize| self.tokens.get(self.current + offset).filter(|t| t.same_line(token));
+
+        let (Some(name), Some(equals), Some(_)) = (on_line(1), on_line(2), on_line(3)) else {
+            return Err(InvalidConstantDefinition { span: token.span() });
+        };
+
+        let is_valid = name.token_type == T::Identifier && equals.token_type == T::Operator && equals.lexeme.trim() == "=";
+        ensure!(is_valid, InvalidConstantDefinitionSnafu { span: token.span() });
+
+        let key = name.lexeme.trim().to_owned();
+
+        // The name is used by another constant, or by a label or symbol in the first pass.
+        let is_defined = self.symbols.constants.contains_key(&key) || (!self.symbol_scanned && self.symbols.offsets.contains_key(&key));
+        ensure!(!is_defined, DuplicateSymbolDefinitionSnafu { span: name.span() });
+
+        self.current += 3;
+        let value = self.expression()?;
+        self.symbols.constants.insert(key, value);
 
         Ok(())
     }
 
     fn save_instruction(&mut self, token: &Token) -> Errorable {
         // Build the instruction from token.
-        let op_str = token.lexeme.clone();
-        let op = self.instruction(&op_str)?;
+        let op = self.instruction(token)?;
 
-        let arity = op.arity() as u16;
+        // Every value on the line is an operand, so `push 5 -1` is not read as `push 5`.
+        if let Some(extra) = self.next_on_line(token) {
+            return Err(ExtraOperand { span: extra.span() });
+        }
 
-        if op == Op::Noop { return Ok(()); }
+        let arity = op.arity() as u16;
 
         self.ops.push(op);
+        self.spans.push(token.span());
         self.code_offset += arity + 1;
 
         Ok(())
     }
 
-    fn instruction(&mut self, op_str: &str) -> Result<Op, ParseError> {
+    fn instruction(&mut self, token: &Token) -> Result<Op, ParseError> {
+        let op_str = token.lexeme.trim();
+        let op = Op::from_str(op_str).map_err(|_| UndefinedInstruction { name: op_str.into(), span: token.span() })?;
+
+        // The 32-bit operand is either one value, such as `push32 100000`, or its high and low words.
+        if op.has_double_word_operand() {
+            self.references.clear();
+
+            if let Some(value) = self.double_word_operand(token) {
+                let mut words = [(value >> 16) as u16, value as u16].into_iter();
+
+                // The address is stored in the low word.
+                if let Some(target) = self.take_reference() {
+                    let offset = self.code_offset + 2;
+                    self.relocations.push(Relocation { segment: Segment::Code, offset, target });
+                }
+
+                return Ok(op.with_arg(|| words.next().unwrap_or(0)));
+            }
+        }
+
         let mut errors: Vec<ParseError> = vec![];
+        let mut references: Vec<Option<RelocationTarget>> = vec![];
 
         let arg_fn = || {
-            self.arg().unwrap_or_else(|err| {
+            self.references.clear();
+
+            let value = self.arg().unwrap_or_else(|err| {
                 errors.push(err);
                 0x00
-            })
+            });
+
+            references.push(self.take_reference());
+            value
         };
 
-        let op = Op::from_str(op_str).map_err(|_| UndefinedInstruction { name: op_str.into() })?;
         let op = op.with_arg(arg_fn);
-        ensure!(errors.is_empty(), InvalidArgumentSnafu {errors});
+        ensure!(errors.is_empty(), InvalidArgumentSnafu { errors, span: token.span() });
+
+        // The operands are stored right after the opcode.
+        for (index, target) in references.into_iter().enumerate() {
+            if let Some(target) = target {
+                let offset = self.code_offset + 1 + index as u16;
+                self.relocations.push(Relocation { segment: Segment::Code, offset, target });
+            }
+        }
 
         Ok(op)
     }
 
+    /// Returns the address symbol that the expression refers to, if it refers to exactly one.
+    /// The distance between two addresses, such as `&end - &start`, stays the same after relocation.
+    fn take_reference(&mut self) -> Option<RelocationTarget> {
+        let mut references = std::mem::take(&mut self.references);
+
+        match references.len() {
+            1 => references.pop(),
+            _ => None,
+        }
+    }
+
+    /// Make the labels and data visible to other files with `.export NAME, ...`
+    fn save_export(&mut self, token: &Token) -> Errorable {
+        for name in self.directive_names(token)? {
+            let key = name.lexeme.trim().to_owned();
+
+            // Labels and data are only known after the first pass.
+            if !self.symbol_scanned {
+                if !self.exports.contains(&key) { self.exports.push(key); }
+                continue;
+            }
+
+            ensure!(self.symbols.offsets.contains_key(&key), UndefinedSymbolsSnafu { span: name.span() });
+        }
+
+        Ok(())
+    }
+
+    /// Refer to the labels and data of other files with `.import NAME, ...`
+    fn save_import(&mut self, token: &Token) -> Errorable {
+        for name in self.directive_names(token)? {
+            let key = name.lexeme.trim().to_owned();
+
+            if !self.symbol_scanned {
+                if !self.imports.contains(&key) { self.imports.push(key); }
+                continue;
+            }
+
+            let is_defined = self.symbols.offsets.contains_key(&key) || self.symbols.constants.contains_key(&key);
+            ensure!(!is_defined, ImportShadowsSymbolSnafu { span: name.span() });
+        }
+
+        Ok(())
+    }
+
+    /// Start running from the label with `.entry NAME`
+    fn save_entry(&mut self, token: &Token) -> Errorable {
+        let names = self.directive_names(token)?;
+        let [name] = &names[..] else { return Err(InvalidEntry { span: token.span() }); };
+        let key = name.lexeme.trim().to_owned();
+
+        if !self.symbol_scanned {
+            self.entry = Some(key);
+            return Ok(());
+        }
+
+        ensure!(self.symbols.offsets.contains_key(&key), UndefinedSymbolsSnafu { span: name.span() });
+        ensure!(!self.symbols.is_data(&key), InvalidEntrySnafu { span: name.span() });
+
+        Ok(())
+    }
+
+    /// Names on the same line as the directive, such as `.export add, sub`
+    fn directive_names(&mut self, token: &Token) -> Result<Vec<Token>, ParseError> {
+        let mut names = vec![];
+
+        while let Some(next) = self.tokens.get(self.current + 1).filter(|t| t.same_line(token)).cloned() {
+            ensure!(next.token_type == T::Identifier, InvalidIdentifierSnafu { span: next.span() });
+
+            names.push(next);
+            self.current += 1;
+        }
+
+        ensure!(!names.is_empty(), InvalidIdentifierSnafu { span: token.span() });
+
+        Ok(names)
+    }
+
+    /// Address of the entry point in the code segment. Starts from the first instruction by default.
+    pub fn entry_address(&self) -> u16 {
+        let offset = self.entry.as_ref().and_then(|key| self.symbols.offsets.get(key));
+
+        self.layout.code_start() + offset.copied().unwrap_or(0)
+    }
+
     fn arg(&mut self) -> Result<u16, ParseError> {
         self.advance();
 
         let token = self.peek()?;
 
         match token.token_type {
-            TokenType::Value(value) => Ok(value),
-            TokenType::Identifier => self.op_arg(&token.clone()),
-            _ => Err(InvalidArgToken)
+            TokenType::Value(..) | TokenType::Identifier | TokenType::Operator => self.expression(),
+            _ => Err(InvalidArgToken { span: token.span() })
         }
     }
 
-    /// Return the memory offset of the label.
+    /// Return the value of the constant, or the memory offset of the label.
     fn op_arg(&mut self, token: &Token) -> Result<u16, ParseError> {
+        let key = token.lexeme.trim();
+        let undefined = || UndefinedSymbols { span: token.span() };
+
+        // Constants are defined in both passes.
+        if let Some(value) = self.symbols.constants.get(key) {
+            return Ok(*value);
+        }
+
         // Return a placeholder for the scanning phase.
         if !self.symbol_scanned { return Ok(0x00); }
 
-        let key = token.lexeme.trim();
-        let offset = self.symbols.offsets.get(key).ok_or(UndefinedSymbols)?;
+        let Some(offset) = self.symbols.offsets.get(key) else {
+            // Imported symbols are resolved by the linker.
+            ensure!(self.imports.iter().any(|name| name == key), UndefinedSymbolsSnafu { span: token.span() });
 
-        // Strings should be loaded from the data segment.
-        if self.symbols.strings.contains_key(key) {
-            return Ok(DATA_START + *offset);
+            self.references.push(RelocationTarget::Import(key.into()));
+            return Ok(0x00);
+        };
+
+        // Strings and arrays should be loaded from the data segment.
+        if self.symbols.strings.contains_key(key) || self.symbols.words.contains_key(key) {
+            let address = self.layout.data_start() + *offset;
+            self.references.push(RelocationTarget::Data);
+            return Ok(address);
         }
 
         // Raw bytes are loaded directly into the code segment.
         if self.symbols.data.contains_key(key) {
-            let value = self.symbols.data.get(key).ok_or(UndefinedSymbols)?;
+            let value = self.symbols.data.get(key).ok_or_else(undefined)?;
 
-            return value.get(0).copied().ok_or(UndefinedSymbols);
+            return value.get(0).copied().ok_or_else(undefined);
         }
 
         // Labels stores the offsets within the code segment.
-        Ok(*offset)
+        let offset = *offset;
+        self.references.push(RelocationTarget::Code);
+        Ok(offset)
+    }
+}
+
+/// Errors in the included files are rendered with the source code of that file.
+impl SourceLookup for Parser {
+    fn source_of(&self, file: Option<&str>) -> Option<&str> {
+        match file {
+            Some(file) => self.sources.get(file).map(|s| s.as_str()),
+            None => Some(&self.source),
+        }
     }
 }
 
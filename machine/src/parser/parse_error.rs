@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
 use tsify::Tsify;
+use super::token::Span;
 
 #[derive(Debug, Snafu, Serialize, Deserialize, PartialEq, Clone, Tsify)]
 #[snafu(visibility(pub))]
@@ -8,56 +9,244 @@ use tsify::Tsify;
 #[tsify(into_wasm_abi, from_wasm_abi, namespace)]
 pub enum ParseError {
     #[snafu(display("string is invalid"))]
-    InvalidString,
+    InvalidString { span: Span },
 
-    #[snafu(display("symbol is not defined"))]
-    UndefinedSymbols,
+    #[snafu(display("symbol '{}' is not defined", span.lexeme))]
+    UndefinedSymbols { span: Span },
 
     #[snafu(display("invalid identifier"))]
-    InvalidIdentifier,
+    InvalidIdentifier { span: Span },
 
     #[snafu(display("instruction '{name}' does not exist!"))]
-    UndefinedInstruction { name: String },
+    UndefinedInstruction { name: String, span: Span },
 
     #[snafu(display("label definition should end with :"))]
-    InvalidLabelDescription,
+    InvalidLabelDescription { span: Span },
 
     #[snafu(display("duplicate label definition"))]
-    DuplicateLabelDefinition,
+    DuplicateLabelDefinition { span: Span },
 
     #[snafu(display("duplicate string definition"))]
-    DuplicateStringDefinition,
+    DuplicateStringDefinition { span: Span },
 
     #[snafu(display("duplicate symbol definition"))]
-    DuplicateSymbolDefinition,
+    DuplicateSymbolDefinition { span: Span },
 
     #[snafu(display("invalid argument"))]
-    InvalidArgument { errors: Vec<ParseError> },
+    InvalidArgument { errors: Vec<ParseError>, span: Span },
 
     #[snafu(display("invalid string value"))]
-    InvalidStringValue,
+    InvalidStringValue { span: Span },
 
     #[snafu(display("invalid byte value"))]
-    InvalidByteValue,
+    InvalidByteValue { span: Span },
 
     #[snafu(display("invalid argument token"))]
-    InvalidArgToken,
+    InvalidArgToken { span: Span },
 
     #[snafu(display("cannot peek at a token"))]
-    CannotPeekAtToken,
+    CannotPeekAtToken { span: Span },
 
     #[snafu(display("peek exceeds source length"))]
-    PeekExceedsSourceLength,
+    PeekExceedsSourceLength { span: Span },
 
     #[snafu(display("invalid decimal digit"))]
-    InvalidDecimalDigit { text: String },
+    InvalidDecimalDigit { span: Span },
 
     #[snafu(display("invalid hex digit"))]
-    InvalidHexDigit { text: String },
+    InvalidHexDigit { span: Span },
+
+    #[snafu(display("invalid binary digit"))]
+    InvalidBinaryDigit { span: Span },
+
+    #[snafu(display("invalid fixed-point literal. it must be less than 128.0q"))]
+    InvalidFixedPoint { span: Span },
+
+    #[snafu(display("invalid character literal"))]
+    InvalidCharacter { span: Span },
+
+    #[snafu(display("invalid operator"))]
+    InvalidOperator { span: Span },
 
     #[snafu(display("scanner reached end of line without terminating"))]
-    ScannerReachedEndOfLine,
+    ScannerReachedEndOfLine { span: Span },
 
     #[snafu(display("program does not contain any instructions to run"))]
-    EmptyProgram,
+    EmptyProgram { span: Span },
+
+    #[snafu(display(".include requires a file path string"))]
+    InvalidInclude { span: Span },
+
+    #[snafu(display("included file '{path}' does not exist"))]
+    IncludeNotFound { path: String, span: Span },
+
+    #[snafu(display("file '{path}' includes itself"))]
+    IncludeCycle { path: String, span: Span },
+
+    #[snafu(display("expected a value after '{}'", span.lexeme))]
+    MissingOperand { span: Span },
+
+    #[snafu(display("'{}' is one operand too many for the instruction", span.lexeme))]
+    ExtraOperand { span: Span },
+
+    #[snafu(display("parenthesis is never closed"))]
+    UnclosedParenthesis { span: Span },
+
+    #[snafu(display("'{left} {} {right}' does not fit in the operand", span.lexeme))]
+    ExpressionOverflow { left: u32, right: u32, span: Span },
+
+    #[snafu(display("'{}' does not fit in 16 bits", span.lexeme))]
+    ValueOverflow { span: Span },
+
+    #[snafu(display("division by zero"))]
+    DivisionByZero { span: Span },
+
+    #[snafu(display("constant definition should be written as .const NAME = value"))]
+    InvalidConstantDefinition { span: Span },
+
+    #[snafu(display("symbol '{}' does not have an address", span.lexeme))]
+    NoAddress { span: Span },
+
+    #[snafu(display("symbol '{}' does not have a value", span.lexeme))]
+    NoValue { span: Span },
+
+    #[snafu(display("variable '{}' can change at runtime, so read it with load instead of *", span.lexeme))]
+    MutableValue { span: Span },
+
+    #[snafu(display("variable definition should be written as .var NAME value"))]
+    InvalidVariableDefinition { span: Span },
+
+    #[snafu(display("alignment must be greater than zero"))]
+    InvalidAlignment { span: Span },
+
+    #[snafu(display("data does not fit in the data segment"))]
+    DataSegmentOverflow { span: Span },
+
+    #[snafu(display("entry point must be a label"))]
+    InvalidEntry { span: Span },
+
+    #[snafu(display("symbol '{}' is imported, but it is also defined in this file", span.lexeme))]
+    ImportShadowsSymbol { span: Span },
+
+    #[snafu(display("macro definition requires a name"))]
+    InvalidMacroDefinition { span: Span },
+
+    #[snafu(display("macro '{name}' cannot shadow an instruction"))]
+    InvalidMacroName { name: String, span: Span },
+
+    #[snafu(display("duplicate macro definition '{name}'"))]
+    DuplicateMacroDefinition { name: String, span: Span },
+
+    #[snafu(display("macro '{name}' is missing .endmacro"))]
+    UnterminatedMacro { name: String, span: Span },
+
+    #[snafu(display("macro '{name}' cannot be defined inside another macro"))]
+    NestedMacroDefinition { name: String, span: Span },
+
+    #[snafu(display(".endmacro without a matching .macro"))]
+    UnexpectedMacroEnd { span: Span },
+
+    #[snafu(display("macro '{name}' expects {expected} arguments, but {given} were given"))]
+    MacroArgumentMismatch { name: String, expected: usize, given: usize, span: Span },
+
+    #[snafu(display("macro '{name}' expands too deeply. is it recursive?"))]
+    MacroRecursionLimit { name: String, span: Span },
+
+    /// The span points to the call site, while the inner error points to the macro body.
+    #[snafu(display("in macro '{name}' called at line {}: {error}", span.line))]
+    MacroExpansionFailed { name: String, error: Box<ParseError>, span: Span },
+}
+
+impl ParseError {
+    /// Location of the error in the source code.
+    pub fn span(&self) -> &Span {
+        use ParseError::*;
+
+        match self {
+            InvalidString { span } | UndefinedSymbols { span } | InvalidIdentifier { span }
+            | UndefinedInstruction { span, .. } | InvalidLabelDescription { span }
+            | DuplicateLabelDefinition { span } | DuplicateStringDefinition { span }
+            | DuplicateSymbolDefinition { span } | InvalidArgument { span, .. }
+            | InvalidStringValue { span } | InvalidByteValue { span } | InvalidArgToken { span }
+            | CannotPeekAtToken { span } | PeekExceedsSourceLength { span }
+            | InvalidDecimalDigit { span } | InvalidHexDigit { span } | InvalidBinaryDigit { span }
+            | InvalidFixedPoint { span }
+            | InvalidCharacter { span } | InvalidOperator { span }
+            | ScannerReachedEndOfLine { span } | EmptyProgram { span }
+            | MissingOperand { span } | ExtraOperand { span } | UnclosedParenthesis { span }
+            | ExpressionOverflow { span, .. } | ValueOverflow { span } | DivisionByZero { span }
+            | InvalidConstantDefinition { span } | InvalidAlignment { span } | DataSegmentOverflow { span }
+            | NoAddress { span } | NoValue { span } | MutableValue { span } | InvalidVariableDefinition { span }
+            | InvalidMacroDefinition { span } | InvalidMacroName { span, .. }
+            | DuplicateMacroDefinition { span, .. } | UnterminatedMacro { span, .. }
+            | NestedMacroDefinition { span, .. } | UnexpectedMacroEnd { span }
+            | MacroArgumentMismatch { span, .. } | MacroRecursionLimit { span, .. }
+            | MacroExpansionFailed { span, .. } | InvalidInclude { span }
+            | IncludeNotFound { span, .. } | IncludeCycle { span, .. }
+            | InvalidEntry { span } | ImportShadowsSymbol { span } => span,
+        }
+    }
+
+    /// Render the error with a caret-annotated snippet of the source code.
+    ///
+    /// ```text
+    /// error: symbol 'ham_cheese' is not defined
+    ///  --> line 1, column 6
+    ///   |
+    /// 1 | push ham_cheese
+    ///   |      ^^^^^^^^^^
+    /// ```
+    pub fn render<S: SourceLookup + ?Sized>(&self, source: &S) -> String {
+        match self {
+            // Point at the macro body first, then at the call site.
+            ParseError::MacroExpansionFailed { name, error, span } => {
+                let mut text = error.render(source);
+                text += &format!("note: expanded from macro '{}'\n", name);
+                text += &render_snippet(source, span);
+                text
+            }
+
+            // Point at each of the invalid arguments.
+            ParseError::InvalidArgument { errors, .. } if !errors.is_empty() => {
+                errors.iter().map(|error| error.render(source)).collect()
+            }
+
+            _ => format!("error: {}\n{}", self, render_snippet(source, self.span())),
+        }
+    }
+}
+
+/// Finds the source code of the file that the span points to.
+pub trait SourceLookup {
+    /// Returns the source code of the included file, or the main source code if `file` is None.
+    fn source_of(&self, file: Option<&str>) -> Option<&str>;
+}
+
+/// The main source code, without any included files.
+impl SourceLookup for str {
+    fn source_of(&self, file: Option<&str>) -> Option<&str> {
+        file.is_none().then_some(self)
+    }
+}
+
+/// Render the source line of the span, with carets underneath the lexeme.
+pub fn render_snippet<S: SourceLookup + ?Sized>(source: &S, span: &Span) -> String {
+    let line_no = span.line.to_string();
+    let gutter = " ".repeat(line_no.len());
+
+    let source = source.source_of(span.file.as_deref()).unwrap_or("");
+    let line = source.lines().nth(span.line.saturating_sub(1)).unwrap_or("");
+    let padding = " ".repeat(span.column.saturating_sub(1));
+    let carets = "^".repeat(span.lexeme.chars().count().max(1));
+
+    let location = match &span.file {
+        Some(file) => format!("{}, line {}, column {}", file, span.line, span.column),
+        None => format!("line {}, column {}", span.line, span.column),
+    };
+
+    let mut text = format!("{}--> {}\n", gutter, location);
+    text += &format!("{} |\n", gutter);
+    text += &format!("{} | {}\n", line_no, line);
+    text += &format!("{} | {}{}\n", gutter, padding, carets);
+    text
 }
@@ -0,0 +1,36 @@
+use strum_macros::FromRepr;
+
+/// Segment that the relocated word is stored in.
+#[derive(Clone, Copy, Debug, PartialEq, FromRepr)]
+#[repr(u16)]
+pub enum Segment {
+    Code = 0,
+    Data = 1,
+}
+
+/// Symbol that the relocated word refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RelocationTarget {
+    /// Label in the code segment of the same file.
+    Code,
+
+    /// Data in the data segment of the same file.
+    Data,
+
+    /// Symbol imported from another file with `.import`
+    Import(String),
+}
+
+/// Word that holds an address, which the linker must adjust once the files are laid out.
+///
+/// The linker adds the base address of the target to the word,
+/// so `jump start` and `push &buf + 2` are both relocatable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relocation {
+    pub segment: Segment,
+
+    /// Offset of the word from the start of the segment.
+    pub offset: u16,
+
+    pub target: RelocationTarget,
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Locates and reads the files referenced by `.include`.
+pub trait FileResolver {
+    /// Resolve the include path into a file name, relative to the including file.
+    /// `from` is None if the include is in the main source code.
+    fn resolve(&self, path: &str, from: Option<&str>) -> String {
+        let base = from.and_then(|f| Path::new(f).parent()).unwrap_or(Path::new(""));
+
+        normalize(&base.join(path))
+    }
+
+    /// Read the source code of the resolved file. Returns None if the file does not exist.
+    fn read(&self, file: &str) -> Option<String>;
+}
+
+/// Reads the included files from the filesystem. Used by the CLI.
+#[derive(Clone, Debug, Default)]
+pub struct FsResolver;
+
+impl FileResolver for FsResolver {
+    fn read(&self, file: &str) -> Option<String> {
+        fs::read_to_string(file).ok()
+    }
+}
+
+/// Reads the included files from memory. Used by the sequencer and the web editor.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    /// Source code of the files, keyed by their file name.
+    pub files: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    pub fn new(files: HashMap<String, String>) -> MemoryResolver {
+        MemoryResolver { files }
+    }
+}
+
+impl FileResolver for MemoryResolver {
+    fn read(&self, file: &str) -> Option<String> {
+        self.files.get(file).cloned()
+    }
+}
+
+/// Remove the `.` and `..` components, so the same file always resolves to the same name.
+fn normalize(path: &Path) -> String {
+    let mut out = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(out.components().next_back(), Some(Component::Normal(_))) => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.to_string_lossy().into_owned()
+}
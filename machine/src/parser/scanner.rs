@@ -1,6 +1,7 @@
+use std::str::FromStr;
 use snafu::ensure;
-use crate::{ParseError, ScannerReachedEndOfLineSnafu};
-use crate::ParseError::{InvalidDecimalDigit, InvalidHexDigit, PeekExceedsSourceLength};
+use crate::{InvalidCharacterSnafu, InvalidFixedPointSnafu, InvalidOperatorSnafu, Op, ParseError, ScannerReachedEndOfLineSnafu};
+use crate::ParseError::{InvalidBinaryDigit, InvalidCharacter, InvalidDecimalDigit, InvalidFixedPoint, InvalidHexDigit, PeekExceedsSourceLength};
 use super::token::*;
 
 type Errorable = Result<(), ParseError>;
@@ -10,12 +11,26 @@ pub struct Scanner {
     pub source: String,
     pub tokens: Vec<Token>,
 
+    /// Byte offsets of the current token and the next character in the source.
     pub start: usize,
     pub current: usize,
     pub line: usize,
 
+    /// Offset where the current line starts.
+    pub line_start: usize,
+
+    /// Line and column where the current token starts.
+    pub start_line: usize,
+    pub start_column: usize,
+
     pub in_instruction: bool,
     pub in_definition: bool,
+
+    /// The instruction takes a 32-bit operand, so its values can be larger than a word.
+    pub in_double_word: bool,
+
+    /// Name of the included file being scanned.
+    pub file: Option<String>,
 }
 
 impl Scanner {
@@ -28,30 +43,71 @@ impl Scanner {
             current: 0,
             line: 0,
 
+            line_start: 0,
+            start_line: 0,
+            start_column: 0,
+
             in_instruction: false,
             in_definition: false,
+            in_double_word: false,
+
+            file: None,
         }
     }
 
+    /// Scan the source code of an included file.
+    pub fn in_file(mut self, file: &str) -> Scanner {
+        self.file = Some(file.into());
+        self
+    }
+
     pub fn scan_tokens(&mut self) -> Errorable {
         while !self.is_end() {
-            self.start = self.current;
+            self.start_token();
             self.scan_token()?;
         }
 
         Ok(())
     }
 
-    fn peek(&self) -> Result<char, ParseError> {
-        if self.is_end() { return Ok('\0'); }
+    /// Scan the tokens, skipping the rest of the line on errors.
+    pub fn scan_tokens_with_recovery(&mut self) -> Vec<ParseError> {
+        let mut errors = vec![];
 
-        self.source.chars().nth(self.current).ok_or(PeekExceedsSourceLength)
+        while !self.is_end() {
+            self.start_token();
+
+            if let Err(error) = self.scan_token() {
+                errors.push(error);
+                self.skip_line();
+            }
+        }
+
+        // Drop the rest of the lines with errors, so they do not cascade into the next line.
+        let error_lines: Vec<usize> = errors.iter().map(|e| e.span().line - 1).collect();
+        self.tokens.retain(|t| !error_lines.contains(&t.line));
+
+        errors
+    }
+
+    fn start_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        // Columns count the characters, so the carets line up under lexemes after non-ASCII text.
+        self.start_column = self.source[self.line_start..self.start].chars().count();
     }
 
-    fn peek_next(&self) -> Result<char, ParseError> {
+    /// Skip to the end of the current line. The newline is scanned as usual.
+    fn skip_line(&mut self) {
+        while !self.is_end() && self.peek() != Ok('\n') {
+            self.current += self.peek().map_or(1, char::len_utf8);
+        }
+    }
+
+    fn peek(&self) -> Result<char, ParseError> {
         if self.is_end() { return Ok('\0'); }
 
-        self.source.chars().nth(self.current + 1).ok_or(PeekExceedsSourceLength)
+        self.source[self.current..].chars().next().ok_or(PeekExceedsSourceLength { span: self.span() })
     }
 
     fn is_end(&self) -> bool {
@@ -59,17 +115,19 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> Result<char, ParseError> {
-        ensure!(!self.is_end(), ScannerReachedEndOfLineSnafu);
+        ensure!(!self.is_end(), ScannerReachedEndOfLineSnafu { span: self.span() });
 
-        let v = self.peek();
-        self.current += 1;
-        v
+        let v = self.peek()?;
+        self.current += v.len_utf8();
+        Ok(v)
     }
 
     fn newline(&mut self) {
         self.line += 1;
+        self.line_start = self.current;
         self.in_instruction = false;
         self.in_definition = false;
+        self.in_double_word = false;
     }
 
     fn scan_token(&mut self) -> Result<(), ParseError> {
@@ -103,6 +161,18 @@ impl Scanner {
                 let token = match &*text {
                     ".string" => Some(TokenType::StringDefinition),
                     ".value" => Some(TokenType::ValueDefinition),
+                    ".var" => Some(TokenType::VariableDefinition),
+                    ".words" => Some(TokenType::WordsDefinition),
+                    ".bytes" => Some(TokenType::BytesDefinition),
+                    ".zero" | ".reserve" => Some(TokenType::ReserveDefinition),
+                    ".align" => Some(TokenType::Align),
+                    ".macro" => Some(TokenType::MacroDefinition),
+                    ".endmacro" => Some(TokenType::MacroEnd),
+                    ".include" => Some(TokenType::Include),
+                    ".const" => Some(TokenType::ConstantDefinition),
+                    ".export" => Some(TokenType::Export),
+                    ".import" => Some(TokenType::Import),
+                    ".entry" => Some(TokenType::Entry),
                     _ => None
                 };
 
@@ -113,27 +183,14 @@ impl Scanner {
                 }
             }
 
-            // Parse hexadecimals.
-            c if c == '0' && !self.is_end() && self.peek()? != ' ' && self.peek_next()? != ' ' => {
+            // Parse hexadecimals and binaries.
+            c if c == '0' && matches!(self.peek()?, 'x' | 'b') => {
                 let char = self.advance()?;
+                self.advance()?;
 
                 match char {
-                    'x' => {
-                        self.advance()?;
-                        self.hex()?
-                    }
-
-                    'b' => {
-                        self.advance()?;
-                        self.binary_digit()?
-                    }
-
-                    '\n' => {
-                        self.decimal()?;
-                        self.newline();
-                    }
-
-                    _ => self.decimal()?,
+                    'x' => self.hex()?,
+                    _ => self.binary_digit()?,
                 }
             }
 
@@ -143,6 +200,22 @@ impl Scanner {
             // Parse identifiers.
             c if is_identifier(c) => self.identifier()?,
 
+            // Parse characters, such as 'A'
+            '\'' => self.character()?,
+
+            // Operators in constant expressions.
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '~' | '(' | ')' | '=' => {
+                self.add_token(TokenType::Operator);
+            }
+
+            // Shift operators, such as "<<"
+            c @ ('<' | '>') => {
+                ensure!(self.peek()? == c, InvalidOperatorSnafu { span: self.span() });
+
+                self.advance()?;
+                self.add_token(TokenType::Operator);
+            }
+
             _ => {}
         }
 
@@ -156,13 +229,43 @@ impl Scanner {
             self.advance()?;
         }
 
+        if matches!(self.peek()?, '.' | 'q') {
+            return self.fixed_point();
+        }
+
         let lexeme = self.peek_lexeme();
-        let number = lexeme.trim().parse::<u16>().map_err(|_| InvalidDecimalDigit { text: lexeme.trim().into() })?;
+        let number = lexeme.trim().parse::<u32>().ok().filter(|n| *n <= self.max_value()).ok_or(InvalidDecimalDigit { span: self.span() })?;
 
         self.add_token(TokenType::Value(number));
         Ok(())
     }
 
+    /// Scan the signed Q8.8 fixed-point literal, such as `1.5q` or `2q`.
+    /// The value is stored with 8 fractional bits, so `1.5q` is 0x0180.
+    fn fixed_point(&mut self) -> Errorable {
+        if self.peek()? == '.' {
+            self.advance()?;
+
+            while self.peek()?.is_ascii_digit() && !self.is_end() {
+                self.advance()?;
+            }
+        }
+
+        ensure!(self.peek()? == 'q', InvalidFixedPointSnafu { span: self.span() });
+        self.advance()?;
+
+        let lexeme = self.peek_lexeme();
+        let number = lexeme.trim().trim_end_matches('q').parse::<f64>().map_err(|_| InvalidFixedPoint { span: self.span() })?;
+
+        // Negative literals are made with the unary minus, such as `-0.5q`.
+        let value = (number * 256.0).round();
+        ensure!(value <= i16::MAX as f64, InvalidFixedPointSnafu { span: self.span() });
+
+        self.add_token(TokenType::Value(value as u32));
+
+        Ok(())
+    }
+
     fn hex(&mut self) -> Errorable {
         while self.peek()?.is_digit(16) && !self.is_end() {
             self.advance()?;
@@ -171,8 +274,8 @@ impl Scanner {
         let text = self.peek_lexeme();
         let text = text.trim();
 
-        let hex_str = text.strip_prefix("0x").ok_or(InvalidHexDigit { text: text.into() })?;
-        let num = u16::from_str_radix(hex_str, 16).map_err(|_| InvalidHexDigit { text: text.into() })?;
+        let hex_str = text.strip_prefix("0x").ok_or(InvalidHexDigit { span: self.span() })?;
+        let num = u32::from_str_radix(hex_str, 16).ok().filter(|n| *n <= self.max_value()).ok_or(InvalidHexDigit { span: self.span() })?;
 
         self.add_token(TokenType::Value(num));
 
@@ -185,20 +288,51 @@ impl Scanner {
         }
 
         let text = self.peek_lexeme();
-        let hex_str = text.strip_prefix("0b").expect("no binary prefix");
-        let num = u16::from_str_radix(hex_str, 2).expect("invalid binary");
+        let text = text.trim();
+
+        let bin_str = text.strip_prefix("0b").ok_or(InvalidBinaryDigit { span: self.span() })?;
+        let num = u32::from_str_radix(bin_str, 2).ok().filter(|n| *n <= self.max_value()).ok_or(InvalidBinaryDigit { span: self.span() })?;
         self.add_token(TokenType::Value(num));
 
         Ok(())
     }
 
+    fn character(&mut self) -> Errorable {
+        // Characters cannot span multiple lines.
+        ensure!(self.peek()? != '\n', InvalidCharacterSnafu { span: self.span() });
+
+        let mut char = self.advance()?;
+
+        if char == '\\' {
+            char = match self.advance()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                c @ ('\\' | '\'') => c,
+                _ => return Err(InvalidCharacter { span: self.span() }),
+            };
+        }
+
+        // The closing quote.
+        ensure!(self.peek()? == '\'', InvalidCharacterSnafu { span: self.span() });
+        self.advance()?;
+
+        let value = u16::try_from(char as u32).map_err(|_| InvalidCharacter { span: self.span() })?;
+        self.add_token(TokenType::Value(value.into()));
+
+        Ok(())
+    }
+
     fn string(&mut self) -> Errorable {
         while self.peek()? != '"' && !self.is_end() {
-            if self.peek()? == '\n' {
+            let is_newline = self.peek()? == '\n';
+            self.advance()?;
+
+            if is_newline {
                 self.line += 1;
+                self.line_start = self.current;
             }
-
-            self.advance()?;
         }
 
         // The closing quotes.
@@ -230,6 +364,7 @@ impl Scanner {
             _ if !self.in_instruction => {
                 self.add_token(TokenType::Instruction);
                 self.in_instruction = true;
+                self.in_double_word = Op::from_str(self.peek_lexeme().trim()).is_ok_and(Op::has_double_word_operand);
             }
 
             _ => {
@@ -244,13 +379,29 @@ impl Scanner {
         self.tokens.push(Token {
             token_type: t,
             lexeme: self.peek_lexeme(),
-            line: self.line,
+            line: self.start_line,
+            column: self.start_column,
+            file: self.file.clone(),
+            expanded_from: None,
         });
     }
 
+    /// Location of the current lexeme.
+    fn span(&self) -> Span {
+        let end = self.current.min(self.source.len());
+        let lexeme = self.source.get(self.start..end).unwrap_or("");
+
+        Span::new(self.start_line, self.start_column, lexeme.trim(), self.file.clone())
+    }
+
     fn peek_lexeme(&self) -> String {
         self.source[self.start..self.current].to_string()
     }
+
+    /// Largest number that fits in the operand, such as 0xFFFF for a word.
+    fn max_value(&self) -> u32 {
+        if self.in_double_word { u32::MAX } else { u16::MAX.into() }
+    }
 }
 
 impl TryFrom<&str> for Scanner {
@@ -285,4 +436,30 @@ mod tests {
         assert_eq!(s.tokens[1].token_type, TokenType::Value(0));
         assert_eq!(s.tokens[2].token_type, TokenType::Value(1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_expression() {
+        let s: Scanner = "push (BUF+'A') << 0b1".try_into().expect("cannot parse expression");
+        let types: Vec<_> = s.tokens.iter().map(|t| t.token_type.clone()).collect();
+
+        assert_eq!(types, [
+            TokenType::Instruction,
+            TokenType::Operator,
+            TokenType::Identifier,
+            TokenType::Operator,
+            TokenType::Value(65),
+            TokenType::Operator,
+            TokenType::Operator,
+            TokenType::Value(1),
+        ]);
+
+        assert_eq!(s.tokens[6].lexeme, "<<");
+    }
+
+    #[test]
+    fn parse_unicode_string() {
+        let s: Scanner = "; héllo\n.string msg \"naïve 🦀\"".try_into().expect("cannot parse unicode string");
+
+        assert_eq!(s.tokens[2].token_type, TokenType::String("naïve 🦀".into()));
+    }
+}
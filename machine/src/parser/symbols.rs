@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::str_to_u16;
 
 /// Symbol table
@@ -12,6 +12,15 @@ pub struct Symbols {
 
     /// Stores the raw bytes for raw data.
     pub data: HashMap<String, Vec<u16>>,
+
+    /// Stores the words of arrays and reserved space, which are addressed by their offset.
+    pub words: HashMap<String, Vec<u16>>,
+
+    /// Stores the values of the constants. They are not written into memory.
+    pub constants: HashMap<String, u16>,
+
+    /// Names of the `.var` cells, which the program can change at runtime.
+    pub variables: HashSet<String>,
 }
 
 impl Symbols {
@@ -20,10 +29,23 @@ impl Symbols {
             offsets: HashMap::new(),
             strings: HashMap::new(),
             data: HashMap::new(),
+            words: HashMap::new(),
+            constants: HashMap::new(),
+            variables: HashSet::new(),
         }
     }
 
-    // TODO: add unit tests for this method, as this is a source for regression bugs.
+    /// Is the symbol stored in the data segment, instead of being a code label or a constant?
+    pub fn is_data(&self, key: &str) -> bool {
+        self.strings.contains_key(key) || self.data.contains_key(key) || self.words.contains_key(key)
+    }
+
+    /// Number of words the data occupies in the data segment.
+    pub fn size(&self, key: &str) -> Option<u16> {
+        self.value(key).map(|v| v.len() as u16)
+    }
+
+    /// Lay out the strings, values and arrays in the data segment by their offsets.
     pub fn bytes(&self) -> Vec<u16> {
         // We must sort the offset table by their offset,
         // otherwise the binary will be out of order.
@@ -33,15 +55,14 @@ impl Symbols {
         let mut data: Vec<u16> = vec![];
 
         let mut write = |offset: usize, bytes: Vec<u16>| {
-            let capacity = offset + bytes.len();
+            let end = offset + bytes.len();
 
-            if data.len() <= capacity {
-                data.resize(capacity + 1, 0);
+            // Gaps between the symbols, such as from alignment, are filled with zeros.
+            if data.len() < end {
+                data.resize(end, 0);
             }
 
-            for (i, byte) in bytes.iter().enumerate() {
-                data.insert(offset + i, *byte);
-            }
+            data[offset..end].copy_from_slice(&bytes);
         };
 
         for (key, offset) in offsets.iter() {
@@ -68,6 +89,31 @@ impl Symbols {
             return Some(value.clone());
         }
 
+        // Arrays and reserved space.
+        if let Some(words) = self.words.get(key) {
+            return Some(words.clone());
+        }
+
         None
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_layout() {
+        let mut symbols = Symbols::new();
+
+        symbols.offsets.insert("msg".into(), 0);
+        symbols.strings.insert("msg".into(), "hi".into());
+
+        symbols.offsets.insert("nums".into(), 4);
+        symbols.words.insert("nums".into(), vec![7, 8]);
+
+        // Labels do not have any data.
+        symbols.offsets.insert("start".into(), 2);
+
+        assert_eq!(symbols.bytes(), [104, 105, 0, 0, 7, 8]);
+    }
+}
@@ -1,3 +1,7 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use super::macros::MacroCall;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum TokenType {
     /// Label definition ends with a colon, such as "start:"
@@ -12,11 +16,50 @@ pub enum TokenType {
     /// Value definition keyword: ".value"
     ValueDefinition,
 
+    /// Mutable variable definition keyword: ".var"
+    VariableDefinition,
+
+    /// Word array definition keyword: ".words"
+    WordsDefinition,
+
+    /// Packed byte array definition keyword: ".bytes"
+    BytesDefinition,
+
+    /// Reserved space definition keyword: ".zero" or ".reserve"
+    ReserveDefinition,
+
+    /// Data alignment keyword: ".align"
+    Align,
+
+    /// Macro definition keyword: ".macro"
+    MacroDefinition,
+
+    /// End of the macro definition: ".endmacro"
+    MacroEnd,
+
+    /// Include directive keyword: ".include"
+    Include,
+
+    /// Constant definition keyword: ".const"
+    ConstantDefinition,
+
+    /// Export directive keyword: ".export"
+    Export,
+
+    /// Import directive keyword: ".import"
+    Import,
+
+    /// Entry point directive keyword: ".entry"
+    Entry,
+
     /// Instruction starts a line, such as "push"
     Instruction,
 
-    /// Value in hex or decimal format, such as "0xFFFF" or "15"
-    Value(u16),
+    /// Value in hex, decimal, binary or character format, such as "0xFFFF", "15" or "'A'"
+    Value(u32),
+
+    /// Operator or parenthesis in a constant expression, such as "+" or "<<"
+    Operator,
 
     /// Name of the label or symbol.
     Identifier,
@@ -30,6 +73,48 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: usize,
+    pub column: usize,
+
+    /// Included file that this token is scanned from.
+    /// None if the token comes from the main source code.
+    pub file: Option<String>,
+
+    /// Macro call site that this token is expanded from.
+    pub expanded_from: Option<MacroCall>,
+}
+
+impl Token {
+    /// Location of the token in the source code.
+    pub fn span(&self) -> Span {
+        Span::new(self.line, self.column, self.lexeme.trim(), self.file.clone())
+    }
+
+    /// Is the other token on the same line of the same file?
+    pub fn same_line(&self, other: &Token) -> bool {
+        self.line == other.line && self.file == other.file
+    }
+}
+
+/// Location of a lexeme in the source code.
+/// Lines and columns start from 1, so they can be shown to the user as-is.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+
+    /// Source text of the offending lexeme.
+    pub lexeme: String,
+
+    /// Included file of the lexeme. None if it is in the main source code.
+    pub file: Option<String>,
+}
+
+impl Span {
+    /// Creates a span from the zero-based line and column.
+    pub fn new(line: usize, column: usize, lexeme: &str, file: Option<String>) -> Span {
+        Span { line: line + 1, column: column + 1, lexeme: lexeme.into(), file }
+    }
 }
 
 pub fn is_identifier(c: char) -> bool {
@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 
 use crate::register::Register::{PC, SP};
-use crate::Register::FP;
-use crate::{CALL_STACK_START, STACK_START};
+use crate::Register::{BP, FP};
+use crate::MemoryLayout;
 
 pub const REG_COUNT: usize = 0xF;
 
@@ -20,8 +20,11 @@ pub enum Register {
     /// Stack Pointer
     SP = 0x02,
 
-    /// Frame Pointer
+    /// Frame Pointer, which is the top of the call stack.
     FP = 0x03,
+
+    /// Base Pointer, which is the start of the stack frame made by `enter`.
+    BP = 0x04,
 }
 
 type R = Register;
@@ -31,7 +34,7 @@ impl Registers {
         let mut v = Registers {
             buffer: vec![0; REG_COUNT],
         };
-        v.reset();
+        v.reset(&MemoryLayout::default());
         v
     }
 
@@ -39,10 +42,12 @@ impl Registers {
         self.buffer[r as usize] = val;
     }
 
-    pub fn reset(&mut self) {
+    /// Reset the registers, with the stack pointers right below their stacks.
+    pub fn reset(&mut self, layout: &MemoryLayout) {
         self.set(PC, 0);
-        self.set(SP, STACK_START - 1);
-        self.set(FP, CALL_STACK_START - 1);
+        self.set(SP, layout.stack_start() - 1);
+        self.set(FP, layout.call_stack_start() - 1);
+        self.set(BP, layout.call_stack_start() - 1);
     }
 
     pub fn get(&self, r: R) -> u16 {
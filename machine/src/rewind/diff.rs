@@ -1,3 +1,5 @@
+use crate::mem::{Memory, PAGE_SIZE};
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Patch<T> {
     pub index: usize,
@@ -22,9 +24,27 @@ pub fn diff_slice<T: PartialEq + Clone>(a: &[T], b: &[T]) -> Vec<Patch<T>> {
     patches
 }
 
+/// Diff the memory page by page. Pages that are still shared since the clone are unchanged, so they are skipped.
+pub fn diff_memory(a: &Memory, b: &Memory) -> Vec<Patch<u16>> {
+    let mut patches: Vec<Patch<u16>> = vec![];
+
+    for page in 0..a.page_count().max(b.page_count()) {
+        if a.shares_page(b, page) { continue; }
+
+        let offset = page * PAGE_SIZE;
+
+        for patch in diff_slice(&a.read_page(page), &b.read_page(page)) {
+            patches.push(Patch { index: offset + patch.index, ..patch })
+        }
+    }
+
+    patches
+}
+
 #[cfg(test)]
 mod diff_tests {
-    use crate::rewind::diff::{diff_slice, Patch};
+    use crate::mem::{Memory, PAGE_SIZE};
+    use crate::rewind::diff::{diff_memory, diff_slice, Patch};
 
     #[test]
     fn diff_test() {
@@ -37,4 +57,19 @@ mod diff_tests {
         let patches = diff_slice(&[1, 2], &[1]);
         assert_eq!(patches[0], Patch { index: 1, from: Some(2), to: None });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn diff_memory_test() {
+        let mut a = Memory::new();
+        a.set(5, 1);
+
+        let mut b = a.clone();
+        b.set(5, 2);
+        b.set(PAGE_SIZE as u16 * 3 + 1, 7);
+
+        assert_eq!(diff_memory(&a, &b), [
+            Patch { index: 5, from: Some(1), to: Some(2) },
+            Patch { index: PAGE_SIZE * 3 + 1, from: Some(0), to: Some(7) },
+        ]);
+    }
+}
@@ -3,7 +3,7 @@ pub mod seq_error;
 
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
-use crate::{Actor, Event, Execute, Machine, Message, Parser};
+use crate::{Actor, Event, Execute, ExecutionLimit, ExecutionLimits, Machine, Memory, MemoryLayout, MemoryResolver, Message, Parser, RuntimeError};
 
 use status::MachineStatus;
 use status::MachineStatus::{Awaiting, Halted, Running};
@@ -28,6 +28,23 @@ pub struct Sequencer {
     /// Are all machines incapable of sending messages?
     /// Use this to prevent the `receive` instruction from blocking forever.
     await_watchdog_counter: u16,
+
+    /// Source code of the files that programs can `.include`, keyed by their file name.
+    #[serde(default)]
+    pub include_files: HashMap<String, String>,
+
+    /// Execution limits of the machines that are added from now on.
+    #[serde(default)]
+    pub default_limits: ExecutionLimits,
+
+    /// Memory layout of the machines that are added from now on.
+    #[serde(default)]
+    default_layout: MemoryLayout,
+
+    /// How many ticks `Canvas::run` runs for, before the machines that are still running are stopped with an error.
+    /// When it is not set, the canvas runs until the machines halt or run out of their own execution limits.
+    #[serde(default)]
+    pub max_run_ticks: Option<u32>,
 }
 
 /// How many cycles should we wait for the message to be received?
@@ -40,15 +57,51 @@ impl Sequencer {
             statuses: HashMap::new(),
             await_watchdog: true,
             await_watchdog_counter: MAX_WAIT_CYCLES,
+            include_files: HashMap::new(),
+            default_limits: ExecutionLimits::unlimited(),
+            default_layout: MemoryLayout::default(),
+            max_run_ticks: None,
         }
     }
 
     /// Add a machine.
-    pub fn add(&mut self, id: u16) {
-        let mut machine = Machine::new();
+    pub fn add(&mut self, id: u16) -> Errorable {
+        let mut machine = Machine::with_layout(self.default_layout).map_err(|error| InvalidMemoryLayout { id, error })?;
         machine.id = Some(id);
+        machine.limits = self.default_limits;
 
         self.machines.push(machine);
+
+        Ok(())
+    }
+
+    /// Lay out the memory of the machines that are added from now on.
+    pub fn set_default_layout(&mut self, layout: MemoryLayout) -> Errorable {
+        layout.validate().map_err(|error| InvalidDefaultMemoryLayout { error })?;
+        self.default_layout = layout;
+
+        Ok(())
+    }
+
+    /// Set the execution limits of the machine.
+    pub fn set_limits(&mut self, id: u16, limits: ExecutionLimits) -> Errorable {
+        let machine = self.get_mut(id).ok_or(MachineDoesNotExist { id })?;
+        machine.limits = limits;
+
+        Ok(())
+    }
+
+    /// Lay out the memory of the machine. This clears the machine, so its program must be loaded again.
+    pub fn set_layout(&mut self, id: u16, layout: MemoryLayout) -> Errorable {
+        layout.validate().map_err(|error| InvalidMemoryLayout { id, error })?;
+
+        let machine = self.get_mut(id).ok_or(MachineDoesNotExist { id })?;
+        machine.mem = Memory::with_layout(layout);
+        machine.full_reset();
+
+        self.statuses.remove(&id);
+
+        Ok(())
     }
 
     /// Remove a machine.
@@ -59,21 +112,25 @@ impl Sequencer {
 
     /// Load the code and symbols into memory.
     pub fn load(&mut self, id: u16, source: &str) -> Errorable {
+        let resolver = MemoryResolver::new(self.include_files.clone());
+
         let machine = self.get_mut(id).ok_or(MachineDoesNotExist { id })?;
         machine.full_reset();
 
-        let parser: Result<Parser, _> = (*source).try_into();
+        let mut parser = Parser::new(source).with_resolver(resolver).with_layout(machine.mem.layout)
+            .map_err(|error| InvalidMemoryLayout { id, error })?;
 
-        let parser = match parser {
-            Ok(parser) => parser,
-            Err(error) => {
-                self.statuses.insert(id, Invalid);
-                return Err(CannotParse { id, error });
-            }
-        };
+        if let Err(error) = parser.parse() {
+            self.statuses.insert(id, Invalid);
+            return Err(CannotParse { id, error });
+        }
+
+        machine.entry = parser.entry_address();
 
-        machine.mem.load_code(parser.ops);
-        machine.mem.load_symbols(parser.symbols);
+        if let Err(error) = machine.mem.load_program(parser.ops, parser.symbols) {
+            self.statuses.insert(id, Invalid);
+            return Err(InvalidMemoryLayout { id, error });
+        }
 
         self.statuses.insert(id, Loaded);
 
@@ -166,7 +223,12 @@ impl Sequencer {
                 // Execute the instruction.
                 machine.tick().map_err(|error| {
                     self.statuses.insert(id, Errored);
-                    ExecutionFailed { id, error: error.into() }
+
+                    match error {
+                        RuntimeError::FuelExhausted { fuel } => ExecutionCycleExceeded { id, limit: ExecutionLimit::Fuel { fuel } },
+                        RuntimeError::CycleLimitExceeded { max_cycles } => ExecutionCycleExceeded { id, limit: ExecutionLimit::Cycles { max_cycles } },
+                        error => ExecutionFailed { id, error },
+                    }
                 })?;
 
                 // If the last instruction is a `receive`,
@@ -210,6 +272,17 @@ impl Sequencer {
         self.statuses.values().all(|s| s == &Halted || s == &Invalid || s == &Errored)
     }
 
+    /// Returns the first machine that is still running, if any.
+    pub fn running_machine(&self) -> Option<u16> {
+        let mut running: Vec<u16> = self.statuses.iter()
+            .filter(|(_, status)| *status == &Running || *status == &Ready)
+            .map(|(id, _)| *id)
+            .collect();
+
+        running.sort();
+        running.first().copied()
+    }
+
     pub fn get_statuses(&self) -> Statuses {
         self.statuses.clone()
     }
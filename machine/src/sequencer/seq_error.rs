@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
 use tsify::Tsify;
-use crate::{ParseError, RuntimeError};
+use crate::{ExecutionLimit, LayoutError, ParseError, RuntimeError};
 
 #[derive(Debug, Snafu, Serialize, Deserialize, PartialEq, Clone, Tsify)]
 #[snafu(visibility(pub))]
@@ -23,6 +23,13 @@ pub enum SequencerError {
     #[snafu(display("program expects a message but they are never received"))]
     MessageNeverReceived { id: u16 },
 
-    ExecutionCycleExceeded { id: u16 },
+    #[snafu(display("machine {id} exceeds its execution limits: {limit:?}"))]
+    ExecutionCycleExceeded { id: u16, limit: ExecutionLimit },
+
+    #[snafu(display("memory layout of machine {id} is invalid: {error}"))]
+    InvalidMemoryLayout { id: u16, error: LayoutError },
+
+    #[snafu(display("default memory layout is invalid: {error}"))]
+    InvalidDefaultMemoryLayout { error: LayoutError },
 }
 
@@ -1,5 +1,5 @@
 use std::fs;
-use crate::Machine;
+use crate::{Execute, Machine, RuntimeError};
 
 pub fn load_test_file(path: &str) -> String {
     let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/tests/asm/" + path;
@@ -9,3 +9,15 @@ pub fn load_test_file(path: &str) -> String {
 pub fn load_test_program(path: &str) -> Machine {
     (*load_test_file(path)).try_into().expect("cannot the compile test program")
 }
+
+pub fn load_test_source(source: &str) -> Machine {
+    source.try_into().expect("cannot compile the test program")
+}
+
+/// Compile and run the program, then return the machine to inspect its state.
+pub fn run_test_source(source: &str) -> Result<Machine, RuntimeError> {
+    let mut m = load_test_source(source);
+    m.run()?;
+
+    Ok(m)
+}
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod addressing_tests {
+    use machine::{run_test_source, load_test_program, Execute, Machine, Op, RuntimeError, CALL_STACK_START, DATA_START};
+    use machine::RuntimeError::{MissingStackFrame, MissingValueToStore};
+
+    type Errorable = Result<(), RuntimeError>;
+
+    #[test]
+    fn test_linked_list() -> Errorable {
+        let mut m: Machine = load_test_program("linked-list.asm");
+        m.run()?;
+
+        assert_eq!(m.mem.get(DATA_START + 6), 21);
+        assert_eq!(m.stack().len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_indirect() -> Errorable {
+        let m = run_test_source(".var a\n.var b\npush 42\npush &b\nstore_i\npush &b\nload_i")?;
+
+        assert_eq!(m.mem.read(DATA_START, 2), [0, 42]);
+        assert_eq!(m.mem.read_stack(1), [42]);
+
+        assert_eq!(run_test_source("push &a\nstore_i\n.var a").err(), Some(MissingValueToStore));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pointer_increment() -> Errorable {
+        // Fill the buffer with 1, 2, 3 through a pointer.
+        let m = run_test_source(".zero buffer 3\npush 1\npush &buffer\nstore_i_inc\npush 2\nswap\nstore_i_inc\npush 3\nswap\nstore_i_inc")?;
+
+        assert_eq!(m.mem.read(DATA_START, 3), [1, 2, 3]);
+        assert_eq!(m.mem.read_stack(1), [DATA_START + 3]);
+
+        let m = run_test_source(".words nums 10 20\npush &nums\nload_i_inc\nswap\nload_i_inc\nswap\npop\npush &nums\ninc_by 5")?;
+        assert_eq!(m.mem.read_stack(3), [10, 20, DATA_START + 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_locals() -> Errorable {
+        let mut m: Machine = vec![Op::Enter(2), Op::Push(5), Op::StoreLocal(1), Op::LoadLocal(1), Op::LoadLocal(0)].into();
+        m.run()?;
+
+        assert_eq!(m.mem.read(CALL_STACK_START + 2, 2), [0, 5]);
+        assert_eq!(m.mem.read_stack(2), [5, 0]);
+
+        // There are no locals outside of a stack frame, so the call stack is left as-is.
+        let mut m: Machine = vec![Op::LoadLocal(0)].into();
+        assert_eq!(m.run(), Err(MissingStackFrame));
+
+        let mut m: Machine = vec![Op::Push(5), Op::StoreLocal(0)].into();
+        assert_eq!(m.run(), Err(MissingStackFrame));
+        assert_eq!(m.mem.read(CALL_STACK_START, 2), [0, 0]);
+
+        Ok(())
+    }
+}
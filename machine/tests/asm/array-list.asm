@@ -0,0 +1,74 @@
+; A list of words on the heap, which doubles its capacity once it is full.
+.var list
+.var len
+.var cap
+.var total
+
+alloc 2
+store list
+push 2
+store cap
+
+; Take the block after the list, so the list has to move to grow.
+alloc 1
+pop
+
+; Append 1 to 10 to the list.
+push 1
+
+append:
+    load len
+    load cap
+    equal
+    jump_zero write
+
+    ; Double the capacity of the full list.
+    load list
+    load cap
+    push 2
+    mul
+    dup
+    store cap
+    realloc
+    store list
+
+write:
+    dup
+    load list
+    load len
+    add
+    store_i          ; list[len] = n
+
+    load len
+    inc
+    store len
+
+    inc
+    dup
+    push 11
+    less_than
+    jump_not_zero append
+
+pop
+
+; Sum the list through a pointer.
+load list
+load len
+
+sum:
+    swap
+    load_i_inc       ; [remaining, pointer + 1, value]
+    load total
+    add
+    store total
+
+    swap
+    dec
+    dup
+    jump_not_zero sum
+
+pop
+pop
+
+load list
+free
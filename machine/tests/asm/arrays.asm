@@ -0,0 +1,17 @@
+.const SIZE = 3
+
+.words nums 10, 20, 30
+.bytes packed 0x12, 0x34, 0x56
+.align 4
+.zero buffer SIZE
+
+; sum the array into the first slot of the buffer.
+load nums
+load nums + 1
+add
+load nums + 2
+add
+store buffer
+
+load buffer
+load packed + 1
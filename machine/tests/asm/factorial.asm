@@ -0,0 +1,26 @@
+; Recursive factorial, following the calling convention of `enter` and `leave`.
+push 5
+call factorial
+nip
+halt
+
+; factorial(n) = n * factorial(n - 1)
+factorial:
+    enter 0
+    load_arg 0
+    jump_zero base_case
+
+    load_arg 0
+    dec
+    call factorial
+    nip                  ; remove the argument of the recursive call
+    load_arg 0
+    mul
+
+    leave
+    return
+
+base_case:
+    push 1
+    leave
+    return
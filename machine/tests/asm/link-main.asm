@@ -0,0 +1,15 @@
+.import double, total
+.entry start
+
+.var count 7
+
+; Skipped, as the program starts from the entry point.
+push 0xFF
+
+start:
+    push 21
+    call double
+    store total
+
+    load total
+    load count
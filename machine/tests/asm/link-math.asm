@@ -0,0 +1,10 @@
+; Library of routines, compiled once and linked into other programs.
+.export double, total
+
+.var total
+
+; Doubles the value on top of the stack.
+double:
+    dup
+    add
+    return
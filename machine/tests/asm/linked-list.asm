@@ -0,0 +1,29 @@
+; Sum the values of a linked list. Each node is [value, next], where the last node has no next node.
+.words nodes 5 0 7 0 9 0
+.var total
+
+; Link the nodes together.
+push &nodes + 2
+push &nodes + 1
+store_i
+
+push &nodes + 4
+push &nodes + 3
+store_i
+
+; Pointer to the current node.
+push &nodes
+
+visit:
+    dup
+    load_i_inc       ; [node, &node.next, node.value]
+    load total
+    add
+    store total
+
+    load_i           ; [node, node.next]
+    nip
+    dup
+    jump_not_zero visit
+
+pop
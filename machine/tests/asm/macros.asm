@@ -0,0 +1,19 @@
+.macro push_sum a b
+    push a
+    push b
+    add
+.endmacro
+
+.macro count_down n
+    push n
+
+loop:
+    dec
+    dup
+    jump_not_zero loop
+    pop
+.endmacro
+
+push_sum 10 20
+count_down 3
+count_down 2
@@ -0,0 +1,54 @@
+; Sum a binary tree with a depth-first traversal.
+; The tree is stored as an array, where the children of the node at i are at 2i + 1 and 2i + 2.
+.const SIZE = 7
+.words tree 1 2 3 4 5 6 7
+
+push 0
+call sum
+nip
+halt
+
+; sum(index) is the sum of the subtree at the index.
+sum:
+    enter 1              ; local 0 is the running total
+    load_arg 0
+    push SIZE
+    less_than
+    jump_zero empty
+
+    ; total = tree[index]
+    push &tree
+    load_arg 0
+    add
+    load_i
+    store_local 0
+
+    ; total += sum(2 * index + 1)
+    load_arg 0
+    push 2
+    mul
+    inc
+    call sum
+    nip
+    load_local 0
+    add
+    store_local 0
+
+    ; total += sum(2 * index + 2)
+    load_arg 0
+    push 2
+    mul
+    push 2
+    add
+    call sum
+    nip
+    load_local 0
+    add
+
+    leave
+    return
+
+empty:
+    push 0
+    leave
+    return
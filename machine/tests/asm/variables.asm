@@ -0,0 +1,18 @@
+.value STEP 5
+.var counter 1
+.var total
+
+; counter += STEP
+load counter
+push STEP
+add
+store counter
+
+; total = counter + the address of STEP
+load counter
+push &STEP
+add
+store total
+
+load counter
+load total
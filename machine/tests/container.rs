@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod container_tests {
+    use machine::{compile_to_binary, load_from_binary, load_test_file, read_segments, Container, Execute, Listing, SectionKind, SymbolEntry, SymbolMap};
+    use machine::cli::CLIError;
+
+    fn hello_world() -> Vec<u16> {
+        compile_to_binary(&load_test_file("hello-world.asm")).expect("cannot compile the test program")
+    }
+
+    #[test]
+    fn test_sections() {
+        let bin = compile_to_binary("jump start\n.string msg \"hi\"\nstart:\nload_string msg\nprint").unwrap();
+        let container = Container::from_words(&bin).expect("cannot read the container");
+
+        let symbols = SymbolMap::from_words(container.section(SectionKind::Symbols).unwrap()).unwrap();
+        assert_eq!(symbols.entries, [
+            SymbolEntry::Label { name: "start".into(), address: 2 },
+            SymbolEntry::Data { name: "msg".into(), address: 0x1000, size: 3 },
+        ]);
+
+        assert_eq!(container.section(SectionKind::DebugLines), Some(&[0, 1, 2, 4, 4, 5][..]));
+        assert_eq!(container.section(SectionKind::Entry), Some(&[0][..]));
+
+        let code = container.section(SectionKind::Code).unwrap();
+        let listing = Listing::from_debug_lines(container.section(SectionKind::DebugLines).unwrap(), code);
+        assert_eq!(listing.entry_at(2).map(|e| (e.line, e.words.len())), Some((4, 2)));
+
+        // The symbol names are stored as UTF-16, so names outside of the basic plane are kept.
+        let bin = compile_to_binary(".string 𝑥 \"hi\"\nload_string 𝑥\nprint").unwrap();
+        let container = Container::from_words(&bin).unwrap();
+        let symbols = SymbolMap::from_words(container.section(SectionKind::Symbols).unwrap()).unwrap();
+        assert_eq!(symbols.entries, [SymbolEntry::Data { name: "𝑥".into(), address: 0x1000, size: 3 }]);
+    }
+
+    #[test]
+    fn test_load_compiled_program() {
+        let mut m = load_from_binary(&hello_world()).expect("cannot load the binary");
+        m.run().expect("cannot run the test program");
+        assert_eq!(m.events.len(), 2);
+    }
+
+    #[test]
+    fn test_corrupt_files() {
+        let bin = hello_world();
+
+        assert!(matches!(read_segments(&[]), Err(CLIError::TruncatedFile)));
+        assert!(matches!(read_segments(&bin[..bin.len() - 1]), Err(CLIError::TruncatedFile)));
+        assert!(matches!(read_segments(&bin[..8]), Err(CLIError::TruncatedFile)));
+
+        let mut wrong_magic = bin.clone();
+        wrong_magic[0] = 0xCAFE;
+        assert!(matches!(read_segments(&wrong_magic), Err(CLIError::IncorrectMagicBytes)));
+
+        let mut wrong_version = bin.clone();
+        wrong_version[2] = 99;
+        assert!(matches!(read_segments(&wrong_version), Err(CLIError::UnsupportedVersion { version: 99 })));
+
+        // Flip a bit in the last word, which belongs to the memory layout section.
+        let mut corrupt = bin.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(matches!(read_segments(&corrupt), Err(CLIError::ChecksumMismatch { kind: 6 })));
+
+        // Invert a byte of the first instruction, which a checksum modulo 255 would miss.
+        let container = Container::from_words(&bin).unwrap();
+        let code_offset = bin.len() - container.sections.iter().map(|s| s.words.len()).sum::<usize>();
+
+        let mut inverted = bin.clone();
+        inverted[code_offset] ^= 0x00FF;
+        assert!(matches!(read_segments(&inverted), Err(CLIError::ChecksumMismatch { kind: 1 })));
+    }
+
+    #[test]
+    fn test_missing_sections() {
+        let mut container = Container::from_words(&hello_world()).unwrap();
+        container.sections.retain(|s| s.kind != SectionKind::Code as u16);
+        assert!(matches!(read_segments(&container.to_words().unwrap()), Err(CLIError::MissingSection { kind: 1 })));
+
+        let mut container = Container::from_words(&hello_world()).unwrap();
+        let layout = container.sections.iter_mut().find(|s| s.kind == SectionKind::MemoryLayout as u16).unwrap();
+        layout.words[0] = 1;
+        assert!(matches!(read_segments(&container.to_words().unwrap()), Err(CLIError::IncompatibleMemoryLayout)));
+
+        // The layout must be valid, so that the machine can be laid out as the binary is assembled for.
+        let mut container = Container::from_words(&hello_world()).unwrap();
+        let layout = container.sections.iter_mut().find(|s| s.kind == SectionKind::MemoryLayout as u16).unwrap();
+        layout.words[4] = 0;
+        assert!(matches!(read_segments(&container.to_words().unwrap()), Err(CLIError::CorruptSection { kind: 6 })));
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod debugger_tests {
+    use machine::{Listing, Parser, SymbolMap, CALL_STACK_START};
+    use machine::cli::{parse_command, DebugCommand, Debugger, StopReason};
+
+    const SOURCE: &str = "\
+.var total
+jump start
+
+add_one:
+    push 1
+    add
+    return
+
+start:
+    push 1
+    call add_one
+    store total
+    push 5
+";
+
+    fn debugger() -> Debugger {
+        let parser: Parser = SOURCE.try_into().expect("cannot compile the test program");
+        let listing = Listing::from(&parser);
+        let symbols = SymbolMap::new(&parser.symbols, &parser.layout);
+
+        Debugger::new(parser.into(), Some(listing), symbols)
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut d = debugger();
+
+        d.execute(&DebugCommand::Break("add_one".into()));
+        assert_eq!(d.resume(|_| false), StopReason::Breakpoint(2));
+        assert_eq!(d.stack(), [1]);
+        assert_eq!(d.return_addresses(), [9]);
+
+        // Stop after the function returns to its caller.
+        assert_eq!(d.finish(), Ok(StopReason::Step));
+        assert_eq!(d.stack(), [2]);
+        assert!(d.return_addresses().is_empty());
+        assert_eq!(d.finish(), Err("not inside a function call".into()));
+
+        assert_eq!(d.resume(|_| false), StopReason::Halted);
+        assert_eq!(d.stack(), [5]);
+    }
+
+    #[test]
+    fn test_step_and_next() {
+        let mut d = debugger();
+
+        // jump start, push 1
+        d.step();
+        d.step();
+        assert_eq!(d.execute(&DebugCommand::Where), "0008  0020 0002        11 | call add_one");
+
+        // Step over the call, then into the next one.
+        assert_eq!(d.step_over(), StopReason::Step);
+        assert_eq!(d.stack(), [2]);
+
+        let mut d = debugger();
+        d.step();
+        d.step();
+        d.step();
+        assert_eq!(d.machine.reg.get(machine::Register::FP), CALL_STACK_START);
+        assert_eq!(d.execute(&DebugCommand::Where), "add_one:\n0002  0001 0001         5 | push 1");
+    }
+
+    #[test]
+    fn test_watches() {
+        let mut d = debugger();
+
+        assert_eq!(d.execute(&DebugCommand::Watch("total".into())), "watching total at 0x1000 = 0");
+        assert_eq!(d.resume(|_| false), StopReason::Watch { name: "total".into(), old: 0, new: 2 });
+
+        assert_eq!(d.execute(&DebugCommand::Memory("total".into(), 2)), "0x1000: 0002 0000");
+        assert_eq!(d.execute(&DebugCommand::Registers), "pc 0x000C  sp 0x40FF  fp 0x3FFF  bp 0x3FFF");
+    }
+
+    #[test]
+    fn test_stack_frames() {
+        let source = "
+    call outer
+    halt
+
+outer:
+    enter 2
+    push 21
+    call inner
+    nip
+    leave
+    return
+
+inner:
+    enter 1
+    load_arg 0
+    push 2
+    mul
+    leave
+    return
+";
+
+        let parser: Parser = source.try_into().expect("cannot compile the test program");
+        let symbols = SymbolMap::new(&parser.symbols, &parser.layout);
+        let mut d = Debugger::new(parser.into(), None, symbols);
+
+        // The locals and the saved registers of the frames are not return addresses.
+        d.execute(&DebugCommand::Break("inner".into()));
+        assert_eq!(d.resume(|_| false), StopReason::Breakpoint(12));
+        d.step();
+        assert_eq!(d.return_addresses(), [1, 8]);
+        assert_eq!(d.execute(&DebugCommand::CallStack), "call stack:\n  inner called from 0x0007\n  outer called from 0x0000");
+
+        // Stop after the function returns, not when it leaves its stack frame.
+        assert_eq!(d.finish(), Ok(StopReason::Step));
+        assert_eq!(d.machine.reg.get(machine::Register::PC), 9);
+        assert_eq!(d.stack(), [21, 42]);
+        assert_eq!(d.return_addresses(), [1]);
+
+        assert_eq!(d.finish(), Ok(StopReason::Step));
+        assert_eq!(d.stack(), [42]);
+        assert!(d.return_addresses().is_empty());
+        assert_eq!(d.finish(), Err("not inside a function call".into()));
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_command("b start"), Ok(DebugCommand::Break("start".into())));
+        assert_eq!(parse_command("x 0x1000 4"), Ok(DebugCommand::Memory("0x1000".into(), 4)));
+        assert_eq!(parse_command("n"), Ok(DebugCommand::Next));
+        assert!(parse_command("break").is_err());
+        assert!(parse_command("fly").is_err());
+
+        let d = debugger();
+        assert_eq!(d.address_of("0x10"), Ok(16));
+        assert_eq!(d.address_of("12"), Ok(12));
+        assert_eq!(d.address_of("start"), Ok(6));
+        assert!(d.address_of("nowhere").is_err());
+    }
+}
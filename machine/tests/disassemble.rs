@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod disassemble_tests {
+    use machine::{compile_parser_to_binary, compile_to_binary, disassemble, load_test_file, read_memory_layout, read_segments, DisassembleError, MemoryLayout, Op, Parser};
+
+    #[test]
+    fn test_round_trip() {
+        let files = ["hello-world.asm", "call-stack-1.asm", "macros.asm", "arrays.asm", "variables.asm", "bitpacking.asm"];
+
+        for file in files {
+            let bin = compile_to_binary(&load_test_file(file)).expect("cannot compile the test program");
+            let (code, data) = read_segments(&bin).expect("cannot read the segments");
+
+            let layout = read_memory_layout(&bin).expect("cannot read the memory layout");
+
+            let source = disassemble(&code, &data, &layout).expect("cannot disassemble the test program");
+            let reassembled = compile_to_binary(&source).expect("cannot assemble the disassembled program");
+
+            // Symbol names and line numbers are not preserved, only the segments are.
+            let segments = read_segments(&reassembled).expect("cannot read the reassembled segments");
+            assert_eq!((code, data), segments, "{} does not round-trip:\n{}", file, source);
+        }
+    }
+
+    #[test]
+    fn test_disassemble_labels_and_data() {
+        let bin = compile_to_binary(".string msg \"hi\"\nnoop\nstart:\nload_string msg\nprint\njump start").unwrap();
+        let (code, data) = read_segments(&bin).unwrap();
+
+        let source = disassemble(&code, &data, &MemoryLayout::default()).unwrap();
+        assert_eq!(source, ".string data_0 \"hi\"\n\nnoop\nlabel_1:\nload_string &data_0\nprint\njump label_1\n");
+    }
+
+    #[test]
+    fn test_disassemble_with_layout() {
+        let layout = MemoryLayout { code_size: 0x100, data_size: 0x40, ..MemoryLayout::default() };
+
+        let mut parser = Parser::new(".string msg \"hi\"\nload_string msg\nprint").with_layout(layout).unwrap();
+        parser.parse().unwrap();
+
+        let bin = compile_parser_to_binary(parser).unwrap();
+        let (code, data) = read_segments(&bin).unwrap();
+
+        // The data starts right after the smaller code segment, so the address is named the same.
+        let source = disassemble(&code, &data, &read_memory_layout(&bin).unwrap()).unwrap();
+        assert_eq!(code[1], 0x100);
+        assert_eq!(source, ".string data_0 \"hi\"\n\nload_string &data_0\nprint\n");
+    }
+
+    #[test]
+    fn test_disassemble_unicode_string() {
+        let bin = compile_to_binary(".string msg \"naïve 🦀\"\n.value v 0xBEEF\n.zero z 2\nnoop").unwrap();
+        let (code, data) = read_segments(&bin).unwrap();
+
+        // Values are not mistaken for text, even if they are valid characters.
+        let source = disassemble(&code, &data, &MemoryLayout::default()).unwrap();
+        assert!(source.starts_with(".string data_0 \"naïve 🦀\"\n.value data_9 0xBEEF\n.zero data_10 2\n"), "{}", source);
+        assert_eq!(read_segments(&compile_to_binary(&source).unwrap()).unwrap(), (code, data));
+    }
+
+    #[test]
+    fn test_disassemble_errors() {
+        let layout = MemoryLayout::default();
+
+        assert_eq!(disassemble(&[0xFFFF], &[], &layout), Err(DisassembleError::UnknownOpcode { opcode: 0xFFFF, offset: 0 }));
+        assert_eq!(disassemble(&[Op::Push(0).opcode()], &[], &layout), Err(DisassembleError::TruncatedInstruction { offset: 0 }));
+        assert_eq!(disassemble(&[Op::Pop.opcode()], &[], &layout), Err(DisassembleError::MissingEndOfFile));
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod double_word_tests {
+    use machine::{run_test_source, compile_to_bytecode, Op, Parser, RuntimeError, DATA_START};
+    use machine::ParseError::{InvalidArgument, InvalidDecimalDigit};
+    use machine::RuntimeError::{CannotDivideByZero, IntegerOverflow, IntegerUnderflow};
+
+    /// Read the stack as 32-bit values.
+    fn stack32(source: &str) -> Result<Vec<u32>, RuntimeError> {
+        let mut m = run_test_source(source)?;
+        let len = m.stack().len();
+
+        Ok(m.mem.read_stack(len).chunks(2).map(|w| ((w[0] as u32) << 16) | w[1] as u32).collect())
+    }
+
+    #[test]
+    fn test_parse_push32() -> Result<(), machine::ParseError> {
+        let mut p = Parser::new("push32 0x0001 0x86A0\ndivmod32\ncmp32");
+        p.parse()?;
+
+        assert_eq!(p.ops, [Op::Push32(100_000), Op::DivMod32, Op::Cmp32]);
+        assert_eq!(compile_to_bytecode(p.ops)[1..3], [0x0001, 0x86A0]);
+        assert_eq!(Op::Push32(100_000).arity(), 2);
+
+        // The operand can also be written as one 32-bit value.
+        let mut p = Parser::new("push32 100000\npush32 -1\npush32 0 -1\npush32 (1 << 20) + 0x86A0");
+        p.parse()?;
+
+        assert_eq!(p.ops, [Op::Push32(100_000), Op::Push32(0xFFFF_FFFF), Op::Push32(0xFFFF), Op::Push32(0x10_86A0)]);
+
+        // Only the 32-bit operands can be larger than a word.
+        assert!(matches!(Parser::new("push 100000").parse(), Err(InvalidDecimalDigit { .. })));
+        assert!(matches!(Parser::new("push32 0x10000 1").parse(), Err(InvalidArgument { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<(), RuntimeError> {
+        assert_eq!(stack32("push32 0 0xFFFF\npush32 0 1\nadd32")?, [0x1_0000]);
+        assert_eq!(stack32("push32 1 0\npush32 0 1\nsub32")?, [0xFFFF]);
+        assert_eq!(stack32("push32 0 1000\npush32 0 1000\nmul32")?, [1_000_000]);
+        assert_eq!(stack32("push32 100000\npush32 7\ndivmod32")?, [14285, 5]);
+        assert_eq!(stack32("push32 0x0001 0x86A0\npush32 0 7\ndivmod32")?, [14285, 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(stack32("push32 0xFFFF 0xFFFF\npush32 0 1\nadd32"), Err(IntegerOverflow));
+        assert_eq!(stack32("push32 0 0\npush32 0 1\nsub32"), Err(IntegerUnderflow));
+        assert_eq!(stack32("push32 0x1000 0\npush32 0 0x10\nmul32"), Err(IntegerOverflow));
+        assert_eq!(stack32("push32 0 1\npush32 0 0\ndivmod32"), Err(CannotDivideByZero));
+    }
+
+    #[test]
+    fn test_compare() -> Result<(), RuntimeError> {
+        let m = run_test_source("push32 1 0\npush32 0 0xFFFF\ncmp32\npush32 0 5\npush32 0 5\ncmp32\npush32 0 2\npush32 3 0\ncmp32")?;
+        assert_eq!(m.mem.read_stack(3), [1, 0, 0xFFFF]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_and_store() -> Result<(), RuntimeError> {
+        let m = run_test_source(".var counter\n.var padding\npush32 0x0001 0x86A0\nstore32 counter\nload32 counter\npush32 0 1\nadd32")?;
+
+        assert_eq!(m.mem.read(DATA_START, 2), [0x0001, 0x86A0]);
+        assert_eq!(m.mem.read_stack(2), [0x0001, 0x86A1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_at_top_of_memory() -> Result<(), RuntimeError> {
+        // The words past the end of the address space are dropped, instead of wrapping around.
+        let m = run_test_source("push32 1 2\nstore32 0xFFFF\npush 3\npush 4\npush 0xFFFE\nwrite 2")?;
+        assert_eq!(m.mem.read(0, 2), m.mem.read_code(2));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod fixed_point_tests {
+    use machine::{run_test_source, Op, ParseError, Parser, RuntimeError, Span};
+    use machine::RuntimeError::{CannotDivideByZero, IntegerOverflow, NegativeSquareRoot};
+
+    fn run(source: &str) -> Result<Vec<i16>, RuntimeError> {
+        let mut m = run_test_source(source)?;
+        let len = m.stack().len();
+        Ok(m.mem.read_stack(len).iter().map(|v| *v as i16).collect())
+    }
+
+    #[test]
+    fn test_fixed_point_literals() -> Result<(), ParseError> {
+        let mut p = Parser::new("push 1.5q\npush 2q\npush -0.25q\npush 0.1q\npush 127.99q");
+        p.parse()?;
+
+        assert_eq!(p.ops, [Op::Push(0x0180), Op::Push(0x0200), Op::Push(0xFFC0), Op::Push(26), Op::Push(0x7FFD)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_literals() {
+        let span = |lexeme: &str| Span { line: 1, column: 6, lexeme: lexeme.into(), file: None };
+
+        assert_eq!(Parser::new("push 128.0q").parse(), Err(ParseError::InvalidFixedPoint { span: span("128.0q") }));
+        assert_eq!(Parser::new("push 1.5").parse(), Err(ParseError::InvalidFixedPoint { span: span("1.5") }));
+    }
+
+    #[test]
+    fn test_multiply_and_divide() -> Result<(), RuntimeError> {
+        assert_eq!(run("push 1.5q\npush 2.5q\nfmul")?, [0x03C0]);
+        assert_eq!(run("push -1.5q\npush 0.5q\nfmul")?, [-0xC0]);
+        assert_eq!(run("push 3q\npush 2q\nfdiv")?, [0x0180]);
+        assert_eq!(run("push 1q\npush -4q\nfdiv")?, [-0x40]);
+
+        // The results are rounded to the nearest value, instead of truncated.
+        assert_eq!(run("push 0x0101\npush 0.5q\nfmul\npush -0x0101\npush 0.5q\nfmul")?, [129, -129]);
+        assert_eq!(run("push 2q\npush 3q\nfdiv\npush -2q\npush 3q\nfdiv")?, [171, -171]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sin_and_sqrt() -> Result<(), RuntimeError> {
+        assert_eq!(run("push 0q\nfsin\npush 1.5708q\nfsin\npush -1.5708q\nfsin")?, [0, 0x0100, -0x0100]);
+        assert_eq!(run("push 2.25q\nfsqrt\npush 2q\nfsqrt")?, [0x0180, 362]);
+
+        // The square root of 0.75 is 221.7 / 256, which rounds up the same as the sine.
+        assert_eq!(run("push 0.75q\nfsqrt")?, [222]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lerp() -> Result<(), RuntimeError> {
+        assert_eq!(run("push 10q\npush 20q\npush 0.25q\nlerp")?, [0x0C80]);
+        assert_eq!(run("push 10q\npush -10q\npush 0.5q\nlerp")?, [0]);
+        assert_eq!(run("push 1q\npush 2q\npush 1q\nlerp")?, [0x0200]);
+        assert_eq!(run("push 0\npush 1\npush 0.5q\nlerp")?, [1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(run("push 100q\npush 2q\nfmul"), Err(IntegerOverflow));
+        assert_eq!(run("push 1q\npush 0\nfdiv"), Err(CannotDivideByZero));
+        assert_eq!(run("push -1q\nfsqrt"), Err(NegativeSquareRoot));
+    }
+}
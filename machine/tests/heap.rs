@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod heap_tests {
+    use machine::{run_test_source, load_test_program, Execute, Machine, RuntimeError, DATA_START, HEAP_HEADER_SIZE, HEAP_START};
+    use machine::RuntimeError::{DoubleFree, InvalidFree, OutOfMemory};
+
+    type Errorable = Result<(), RuntimeError>;
+
+    #[test]
+    fn test_array_list() -> Errorable {
+        let mut m: Machine = load_test_program("array-list.asm");
+        m.run()?;
+
+        // len, cap and total.
+        assert_eq!(m.mem.read(DATA_START + 1, 3), [10, 16, 55]);
+        assert_eq!(m.stack().len(), 0);
+
+        // Only the block after the list is left.
+        assert_eq!(m.heap().used(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_alloc_and_free() -> Errorable {
+        let first = HEAP_START + HEAP_HEADER_SIZE;
+
+        let mut m = run_test_source("alloc 3\nalloc 1\nswap\nfree\nalloc 2")?;
+        assert_eq!(m.mem.read_stack(2), [first + 3 + HEAP_HEADER_SIZE, first]);
+
+        // The freed block is too small to split, so the new block takes all of it.
+        assert_eq!(m.heap().used(), 4);
+
+        // Resizing the null address allocates.
+        let mut m = run_test_source("push 0\npush 4\nrealloc")?;
+        assert_eq!(m.mem.read_stack(1), [first]);
+
+        m.partial_reset();
+        assert_eq!(m.heap().used(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_heap_errors() {
+        assert_eq!(run_test_source("alloc 1\ndup\nfree\nfree").err(), Some(DoubleFree { address: HEAP_START + HEAP_HEADER_SIZE }));
+        assert_eq!(run_test_source("push 0x1000\nfree").err(), Some(InvalidFree { address: 0x1000 }));
+        assert_eq!(run_test_source("alloc 0x4000").err(), Some(OutOfMemory { size: 0x4000 }));
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod layout_tests {
+    use machine::{compile_parser_to_binary, load_from_binary, load_from_binary_with_layout, CannotParse, Execute, InvalidDefaultMemoryLayout, InvalidMemoryLayout, LayoutError, Machine, MemoryLayout, ParseError, Parser, RuntimeError, Span, HEAP_HEADER_SIZE};
+    use machine::canvas::{Canvas, CanvasError, CanvasError::MachineError};
+    use machine::cli::CLIError;
+
+    type Errorable = Result<(), CanvasError>;
+
+    const SMALL: MemoryLayout = MemoryLayout {
+        code_size: 0x100,
+        data_size: 0x40,
+        mapped_size: 0x400,
+        call_stack_size: 0x20,
+        stack_size: 0x80,
+        heap_size: 0x100,
+    };
+
+    const PROGRAM: &str = "
+        .var total
+        push 2
+        push 3
+        add
+        store total
+        alloc 4
+    ";
+
+    fn parser(source: &str) -> Parser {
+        let mut parser = Parser::new(source).with_layout(SMALL).expect("invalid memory layout");
+        parser.parse().expect("cannot compile the test program");
+        parser
+    }
+
+    #[test]
+    fn test_small_machine() -> Result<(), RuntimeError> {
+        let mut m: Machine = parser(PROGRAM).into();
+        m.run()?;
+
+        assert_eq!(m.mem.size(), 0x100 + 0x40 + 0x400 + 0x20 + 0x80 + 0x100);
+        assert_eq!(m.mem.get(0x100), 5, "data should start right after the code");
+        assert_eq!(m.mem.read_stack(1), [SMALL.heap_start() + HEAP_HEADER_SIZE]);
+
+        // The stack overflows at the end of its own segment.
+        let mut m: Machine = parser("loop:\npush 1\njump loop").into();
+        assert_eq!(m.run(), Err(RuntimeError::StackOverflow { top: SMALL.stack_end(), max: SMALL.stack_end() }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequencer_layout() -> Errorable {
+        let mut c = Canvas::new();
+        c.seq.set_default_layout(SMALL).expect("invalid memory layout");
+        c.add_machine()?;
+        c.load_program(0, PROGRAM)?;
+        c.run()?;
+
+        assert_eq!(c.seq.get(0).map(|m| m.mem.get(0x100)), Some(5));
+
+        // The program must fit in the segments of the machine.
+        let error = LayoutError::SegmentOverflow { segment: "code".into(), size: 0x103, max: 0x100 };
+        assert_eq!(c.load_program(0, &"push 1\n".repeat(0x81)), Err(MachineError { cause: InvalidMemoryLayout { id: 0, error } }));
+
+        // The data is checked against the data segment as it is assembled.
+        let error = ParseError::DataSegmentOverflow { span: Span { line: 1, column: 1, lexeme: ".zero".into(), file: None } };
+        assert_eq!(c.load_program(0, ".zero buffer 0x41\npush 1"), Err(MachineError { cause: CannotParse { id: 0, error } }));
+
+        let error = LayoutError::EmptySegment { segment: "stack".into() };
+        let layout = MemoryLayout { stack_size: 0, ..SMALL };
+        assert_eq!(c.seq.set_layout(0, layout), Err(InvalidMemoryLayout { id: 0, error }));
+
+        c.seq.set_layout(0, MemoryLayout::default()).expect("cannot lay out the memory");
+        assert_eq!(c.seq.get(0).map(|m| m.mem.size()), Some(MemoryLayout::default().size()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        let layout = MemoryLayout { stack_size: 0xFFFF, ..MemoryLayout::default() };
+        let error = LayoutError::MemoryTooLarge { size: layout.section_words().iter().map(|size| *size as usize).sum() };
+
+        let mut c = Canvas::new();
+        assert_eq!(c.seq.set_default_layout(layout), Err(InvalidDefaultMemoryLayout { error: error.clone() }));
+
+        // The machines are still added with the previous default layout.
+        assert!(c.add_machine().is_ok());
+        assert_eq!(c.seq.get(0).map(|m| m.mem.layout), Some(MemoryLayout::default()));
+
+        assert_eq!(Machine::with_layout(layout).err(), Some(error.clone()));
+        assert_eq!(Parser::new("push 1").with_layout(layout).err(), Some(error));
+    }
+
+    #[test]
+    fn test_binary_layout() {
+        let bin = compile_parser_to_binary(parser(PROGRAM)).expect("cannot compile the test program");
+
+        // The machine is laid out as the binary is assembled for.
+        let mut m = load_from_binary(&bin).expect("cannot load the binary");
+        m.run().expect("cannot run the binary");
+        assert_eq!(m.mem.layout, SMALL);
+        assert_eq!(m.mem.get(0x100), 5);
+
+        assert!(load_from_binary_with_layout(&bin, SMALL).is_ok());
+        assert!(matches!(load_from_binary_with_layout(&bin, MemoryLayout::default()), Err(CLIError::IncompatibleMemoryLayout)));
+    }
+}
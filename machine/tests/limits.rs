@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod limits_tests {
+    use machine::{load_test_source, Execute, ExecutionCycleExceeded, ExecutionLimit, ExecutionLimits, RuntimeError};
+    use machine::canvas::{Canvas, CanvasError, CanvasError::MachineError};
+    use machine::status::MachineStatus::{Errored, Halted};
+
+    type Errorable = Result<(), CanvasError>;
+
+    const INFINITE_LOOP: &str = "
+        loop:
+            push 1
+            pop
+            jump loop
+    ";
+
+    #[test]
+    fn test_fuel() {
+        let mut m = load_test_source(INFINITE_LOOP);
+        m.limits = ExecutionLimits::new(Some(10), None);
+
+        assert_eq!(m.run(), Err(RuntimeError::FuelExhausted { fuel: 10 }));
+        assert_eq!(m.instructions, 10);
+    }
+
+    #[test]
+    fn test_max_cycles() {
+        let mut m = load_test_source(INFINITE_LOOP);
+        m.limits = ExecutionLimits::new(None, Some(12));
+
+        // Each round of the loop takes 5 cycles, so the `pop` in the third round does not fit.
+        assert_eq!(m.run(), Err(RuntimeError::CycleLimitExceeded { max_cycles: 12 }));
+        assert_eq!(m.cycles, 12);
+        assert_eq!(m.instructions, 7);
+    }
+
+    #[test]
+    fn test_resume_after_raising_limits() {
+        let mut m = load_test_source("push 1\npush 2\nadd");
+        m.limits = ExecutionLimits::new(Some(2), None);
+
+        assert!(m.tick().is_ok());
+        assert!(m.tick().is_ok());
+        assert_eq!(m.tick(), Err(RuntimeError::FuelExhausted { fuel: 2 }));
+
+        // The machine stays on the instruction that is out of fuel.
+        m.limits = ExecutionLimits::unlimited();
+        assert!(m.tick().is_ok());
+        assert_eq!(m.mem.read_stack(1), [3]);
+    }
+
+    #[test]
+    fn test_reset_counters() {
+        let mut m = load_test_source("push 1\npush 2\nadd");
+        m.run().expect("cannot run the test program");
+        assert_eq!((m.instructions, m.cycles), (3, 5));
+
+        m.partial_reset();
+        assert_eq!((m.instructions, m.cycles), (0, 0));
+    }
+
+    #[test]
+    fn test_sequencer_limits() -> Errorable {
+        let mut c = Canvas::new();
+        c.add_machine()?;
+        c.load_program(0, INFINITE_LOOP)?;
+        c.seq.set_limits(0, ExecutionLimits::new(Some(50), None)).expect("cannot set the limits");
+
+        assert_eq!(c.run(), Err(MachineError { cause: ExecutionCycleExceeded { id: 0, limit: ExecutionLimit::Fuel { fuel: 50 } } }));
+        assert_eq!(c.seq.statuses[&0], Errored);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_limits() -> Errorable {
+        let mut c = Canvas::new();
+        c.seq.default_limits = ExecutionLimits::new(None, Some(100));
+        c.add_machine()?;
+
+        assert_eq!(c.seq.get(0).map(|m| m.limits.max_cycles), Some(Some(100)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_canvas_run_does_not_stop_silently() -> Errorable {
+        let mut c = Canvas::new();
+        c.add_machine()?;
+        c.add_machine()?;
+        c.load_program(0, INFINITE_LOOP)?;
+        c.load_program(1, "push 1")?;
+        c.seq.max_run_ticks = Some(1000);
+
+        let limit = ExecutionLimit::RunTicks { ticks: 1000 };
+        assert_eq!(c.run(), Err(MachineError { cause: ExecutionCycleExceeded { id: 0, limit } }));
+        assert_eq!(c.seq.statuses[&1], Halted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_canvas_runs_long_programs() -> Errorable {
+        let mut c = Canvas::new();
+        c.add_machine()?;
+
+        // Counts down from 2000, which takes more ticks than the old fixed limit of 1000.
+        c.load_program(0, "push 2000\nloop:\ndec\ndup\njump_not_zero loop")?;
+        c.seq.set_limits(0, ExecutionLimits::new(None, Some(100_000))).expect("cannot set the limits");
+
+        c.run()?;
+        assert_eq!(c.seq.statuses[&0], Halted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequencer_cycle_limit() -> Errorable {
+        let mut c = Canvas::new();
+        c.add_machine()?;
+        c.load_program(0, INFINITE_LOOP)?;
+        c.seq.set_limits(0, ExecutionLimits::new(None, Some(40))).expect("cannot set the limits");
+
+        let limit = ExecutionLimit::Cycles { max_cycles: 40 };
+        assert_eq!(c.run(), Err(MachineError { cause: ExecutionCycleExceeded { id: 0, limit } }));
+
+        Ok(())
+    }
+}
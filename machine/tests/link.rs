@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod link_tests {
+    use machine::{link, load_from_binary, load_test_file, read_memory_layout, read_segments, Execute, LinkError, Machine, MemoryLayout, ObjectFile, ParseError, Parser, Relocation, RelocationTarget, Segment, Sequencer, DATA_START};
+    use machine::cli::CLIError;
+
+    fn object(source: &str) -> ObjectFile {
+        let parser: Parser = source.try_into().expect("cannot compile the object file");
+        ObjectFile::from(parser)
+    }
+
+    fn test_object(path: &str) -> ObjectFile {
+        let object = object(&load_test_file(path));
+
+        // Object files are written to and read back from the disk.
+        ObjectFile::from_words(&object.to_words().unwrap()).expect("cannot read the object file")
+    }
+
+    #[test]
+    fn test_link_and_run() {
+        let objects = [test_object("link-math.asm"), test_object("link-main.asm")];
+        let bin = link(&objects, None).expect("cannot link the object files");
+
+        let mut m = load_from_binary(&bin).expect("cannot load the linked program");
+        m.run().expect("cannot run the linked program");
+
+        assert_eq!(m.mem.read_stack(2), [42, 7]);
+    }
+
+    #[test]
+    fn test_relocations() {
+        let main = test_object("link-main.asm");
+
+        assert_eq!(main.imports, ["double", "total"]);
+        assert_eq!(main.entry, Some(2));
+
+        assert_eq!(main.relocations, [
+            Relocation { segment: Segment::Code, offset: 5, target: RelocationTarget::Import("double".into()) },
+            Relocation { segment: Segment::Code, offset: 7, target: RelocationTarget::Import("total".into()) },
+            Relocation { segment: Segment::Code, offset: 9, target: RelocationTarget::Import("total".into()) },
+            Relocation { segment: Segment::Code, offset: 11, target: RelocationTarget::Data },
+        ]);
+
+        // Addresses in the data segment are relocated too, while the distance between two addresses is not.
+        let table = object(".words table &start\n.words size &end - &start\nstart:\npush 1\nend:");
+        assert_eq!(table.relocations, [Relocation { segment: Segment::Data, offset: 0, target: RelocationTarget::Code }]);
+    }
+
+    #[test]
+    fn test_link_layout() {
+        let objects = [test_object("link-math.asm"), test_object("link-main.asm")];
+        let bin = link(&objects, None).unwrap();
+        let (code, data) = read_segments(&bin).unwrap();
+
+        // The math library comes first, so `count` is stored after `total`.
+        assert_eq!(data, [0, 7]);
+        assert_eq!(code[code.len() - 2], DATA_START + 1);
+
+        // The end-of-file marker of the math library is dropped.
+        let m = load_from_binary(&bin).unwrap();
+        assert_eq!(m.entry, 3 + 2);
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let mut m: Machine = ".entry start\npush 1\nstart:\npush 2".try_into().unwrap();
+        m.run().unwrap();
+        assert_eq!(m.mem.read_stack(2), [2, 0]);
+
+        let mut seq = Sequencer::new();
+        seq.add(0).unwrap();
+        seq.load(0, ".entry start\npush 1\nstart:\npush 2").unwrap();
+        seq.ready();
+        seq.step(2).unwrap();
+        assert_eq!(seq.get(0).unwrap().mem.read_stack(2), [2, 0]);
+
+        // The entry point given to the linker must be an exported label.
+        let library = object(".export helper\nhelper:\npush 3");
+        let main = object("push 4");
+
+        let mut m = load_from_binary(&link(&[main.clone(), library.clone()], Some("helper")).unwrap()).unwrap();
+        m.run().unwrap();
+        assert_eq!(m.mem.read_stack(2), [3, 0]);
+
+        assert_eq!(link(&[main.clone()], Some("helper")), Err(LinkError::UndefinedEntry { name: "helper".into() }));
+        assert_eq!(link(&[object(".entry a\na:\npush 1"), object(".entry b\nb:\npush 2")], None), Err(LinkError::DuplicateEntry));
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let main = test_object("link-main.asm");
+        let math = test_object("link-math.asm");
+
+        assert_eq!(link(&[], None), Err(LinkError::NoObjectFiles));
+        assert_eq!(link(&[main.clone()], None), Err(LinkError::UndefinedSymbol { name: "double".into() }));
+        assert_eq!(link(&[math.clone(), math, main], None), Err(LinkError::DuplicateSymbol { name: "double".into() }));
+
+        // Executables cannot be linked, and object files cannot be run.
+        let executable = machine::compile_to_binary("push 1").unwrap();
+        assert!(matches!(ObjectFile::from_words(&executable), Err(CLIError::NotObjectFile)));
+        assert!(matches!(load_from_binary(&object("push 1").to_words().unwrap()), Err(CLIError::NotExecutable)));
+    }
+
+    #[test]
+    fn test_linkage_directive_errors() {
+        let parse = |source: &str| Parser::try_from(source).err();
+
+        assert!(matches!(parse(".import foo\nfoo:\npush 1"), Some(ParseError::ImportShadowsSymbol { .. })));
+        assert!(matches!(parse(".export missing\npush 1"), Some(ParseError::UndefinedSymbols { .. })));
+        assert!(matches!(parse(".var msg\n.entry msg\npush 1"), Some(ParseError::InvalidEntry { .. })));
+        assert!(matches!(parse(".entry a, b\na:\nb:\npush 1"), Some(ParseError::InvalidEntry { .. })));
+        assert!(matches!(parse(".export\npush 1"), Some(ParseError::InvalidIdentifier { .. })));
+    }
+
+    #[test]
+    fn test_link_with_layout() {
+        let layout = MemoryLayout { code_size: 0x100, data_size: 0x40, ..MemoryLayout::default() };
+
+        let object_with_layout = |source: &str| {
+            let mut parser = Parser::new(source).with_layout(layout).expect("invalid memory layout");
+            parser.parse().expect("cannot compile the object file");
+
+            let object = ObjectFile::from(parser);
+            ObjectFile::from_words(&object.to_words().unwrap()).expect("cannot read the object file")
+        };
+
+        let library = object_with_layout(".export total\n.var total 7\nnoop");
+        let main = object_with_layout(".import total\nload total\npush 1\nadd");
+        assert_eq!(main.layout, layout);
+
+        // The executable is linked for the layout of the object files.
+        let bin = link(&[library.clone(), main], None).expect("cannot link the object files");
+        assert_eq!(read_memory_layout(&bin).unwrap(), layout);
+
+        let mut m = load_from_binary(&bin).expect("cannot load the linked program");
+        m.run().expect("cannot run the linked program");
+        assert_eq!(m.mem.read_stack(1), [8]);
+
+        // Object files assembled for different layouts cannot be linked together.
+        assert_eq!(link(&[library, object("push 1")], None), Err(LinkError::LayoutMismatch));
+
+        // The linked program must fit in the segments of the layout.
+        let big = object_with_layout(&"push 1\n".repeat(0x81));
+        assert_eq!(link(&[big], None), Err(LinkError::SegmentOverflow));
+    }
+}
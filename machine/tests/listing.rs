@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod listing_tests {
+    use machine::{load_test_file, Listing, MemoryLayout, Op, ParseError, Parser, SymbolEntry, SymbolMap, DATA_START};
+
+    type Errorable = Result<(), ParseError>;
+
+    #[test]
+    fn test_listing() -> Errorable {
+        let p: Parser = (*load_test_file("call-stack-1.asm")).try_into()?;
+        let listing = Listing::from(&p);
+
+        let entry = listing.entry_at(0x09).expect("missing entry for the call");
+        assert_eq!(entry.words, [0x20, 0x02]);
+        assert_eq!(entry.line, 10);
+        assert_eq!(entry.source, "call add_pattern");
+
+        // Addresses in the middle of an instruction do not have an entry.
+        assert_eq!(listing.entry_at(0x01), None);
+
+        assert_eq!(entry.to_string(), "0009  0020 0002        10 | call add_pattern");
+
+        // The rendered listing can be read back.
+        assert_eq!(Listing::parse(&listing.render()), listing);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbol_map() -> Errorable {
+        let p: Parser = (*load_test_file("arrays.asm")).try_into()?;
+        let map = SymbolMap::new(&p.symbols, &p.layout);
+
+        assert_eq!(map.entries[0], SymbolEntry::Data { name: "nums".into(), address: DATA_START, size: 3 });
+        assert_eq!(map.entries[2], SymbolEntry::Data { name: "buffer".into(), address: DATA_START + 8, size: 3 });
+
+        assert_eq!(map.render().lines().nth(1), Some("data packed 0x1003 2"));
+
+        // The addresses follow the memory layout that the program is assembled for.
+        let layout = MemoryLayout { code_size: 0x100, data_size: 0x40, ..MemoryLayout::default() };
+        let mut p = Parser::new(".words nums 1, 2\nstart:\nload nums").with_layout(layout).expect("invalid memory layout");
+        p.parse()?;
+
+        let map = SymbolMap::new(&p.symbols, &p.layout);
+        assert_eq!(p.ops[0], Op::Load(0x100));
+        assert_eq!(map.entries, [
+            SymbolEntry::Label { name: "start".into(), address: 0 },
+            SymbolEntry::Data { name: "nums".into(), address: 0x100, size: 2 },
+        ]);
+
+        Ok(())
+    }
+}
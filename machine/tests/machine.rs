@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod machine_tests {
-    use machine::{Execute, Machine as M, Op};
+    use machine::{load_test_program, Execute, Machine as M, Op, DATA_START};
 
     #[test]
     fn test_run_machine() {
@@ -9,4 +9,29 @@ mod machine_tests {
 
         assert_eq!(m.stack().peek(), 7);
     }
+
+    #[test]
+    fn test_run_macros() {
+        let mut m = load_test_program("macros.asm");
+        m.run().expect("cannot run the test program");
+
+        assert_eq!(m.mem.read_stack(2), [30, 0]);
+    }
+
+    #[test]
+    fn test_run_arrays() {
+        let mut m = load_test_program("arrays.asm");
+        m.run().expect("cannot run the test program");
+
+        assert_eq!(m.mem.read_stack(2), [60, 0x5600]);
+    }
+
+    #[test]
+    fn test_run_variables() {
+        let mut m = load_test_program("variables.asm");
+        m.run().expect("cannot run the test program");
+
+        // STEP is stored at the start of the data segment.
+        assert_eq!(m.mem.read_stack(2), [6, 6 + DATA_START]);
+    }
 }
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use machine::{Execute, Machine as M, Op, RuntimeError};
-    use machine::RuntimeError::{CannotDivideByZero, IntegerOverflow};
+    use machine::{run_test_source, Execute, Machine as M, Op, RuntimeError};
+    use machine::RuntimeError::{CannotDivideByZero, IntegerOverflow, IntegerUnderflow};
 
     type Errorable = Result<(), RuntimeError>;
 
@@ -42,4 +42,69 @@ mod tests {
 
         Ok(())
     }
+
+    /// Run the program, then return the stack as signed integers.
+    fn run_signed(source: &str) -> Result<Vec<i16>, RuntimeError> {
+        let mut m = run_test_source(source)?;
+        let len = m.stack().len();
+        Ok(m.mem.read_stack(len).iter().map(|v| *v as i16).collect())
+    }
+
+    #[test]
+    fn test_signed_arithmetic() -> Errorable {
+        assert_eq!(run_signed("push -5\npush 3\nadd_s")?, [-2]);
+        assert_eq!(run_signed("push 3\npush 5\nsub_s")?, [-2]);
+        assert_eq!(run_signed("push -4\npush 3\nmul_s")?, [-12]);
+        assert_eq!(run_signed("push -7\npush 2\ndiv_s")?, [-3]);
+        assert_eq!(run_signed("push -7\npush 2\nmod_s")?, [-1]);
+        assert_eq!(run_signed("push 5\nneg\npush -3\nneg")?, [-5, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_overflow() {
+        assert_eq!(run_signed("push 32767\npush 1\nadd_s"), Err(IntegerOverflow));
+        assert_eq!(run_signed("push -32768\npush 1\nsub_s"), Err(IntegerUnderflow));
+        assert_eq!(run_signed("push -200\npush 200\nmul_s"), Err(IntegerUnderflow));
+        assert_eq!(run_signed("push -32768\npush -1\ndiv_s"), Err(IntegerOverflow));
+        assert_eq!(run_signed("push -32768\nneg"), Err(IntegerOverflow));
+        assert_eq!(run_signed("push 1\npush 0\nmod_s"), Err(CannotDivideByZero));
+    }
+
+    #[test]
+    fn test_wrapping_and_saturating() -> Errorable {
+        assert_eq!(run_signed("push 0xFFFF\npush 2\nadd_wrap")?, [1]);
+        assert_eq!(run_signed("push 1\npush 2\nsub_wrap")?, [-1]);
+        assert_eq!(run_signed("push 0x100\npush 0x100\nmul_wrap")?, [0]);
+
+        assert_eq!(run_signed("push 0xFFF0\npush 0x20\nadd_sat")?, [-1]);
+        assert_eq!(run_signed("push 1\npush 2\nsub_sat")?, [0]);
+        assert_eq!(run_signed("push 0x100\npush 0x100\nmul_sat")?, [-1]);
+
+        assert_eq!(run_signed("push 32000\npush 1000\nadd_sat_s")?, [32767]);
+        assert_eq!(run_signed("push -32000\npush 1000\nsub_sat_s")?, [-32768]);
+        assert_eq!(run_signed("push -300\npush 300\nmul_sat_s")?, [-32768]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_comparisons() -> Errorable {
+        assert_eq!(run_signed("push -1\npush 1\nless_than_s\npush -1\npush 1\nless_than")?, [1, 0]);
+        assert_eq!(run_signed("push -1\npush -1\nless_than_or_equal_s")?, [1]);
+        assert_eq!(run_signed("push 2\npush -3\ngreater_than_s")?, [1]);
+        assert_eq!(run_signed("push -3\npush 2\ngreater_than_or_equal_s")?, [0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic_right_shift() -> Errorable {
+        assert_eq!(run_signed("push -16\npush 2\narithmetic_right_shift")?, [-4]);
+        assert_eq!(run_signed("push 16\npush 2\narithmetic_right_shift")?, [4]);
+        assert_eq!(run_signed("push -16\npush 20\narithmetic_right_shift")?, [-1]);
+
+        Ok(())
+    }
 }
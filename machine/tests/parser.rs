@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod parser_tests {
-    use machine::{load_test_file, Op, ParseError, Parser};
-    use machine::ParseError::{EmptyProgram, InvalidArgument, UndefinedSymbols};
+    use std::collections::HashMap;
+    use machine::{load_test_file, MemoryResolver, Op, ParseError, ParseWarning, Parser, Span, DATA_START};
+    use machine::ParseError::{DataSegmentOverflow, DivisionByZero, EmptyProgram, ExpressionOverflow, ExtraOperand, IncludeCycle, IncludeNotFound, InvalidAlignment, InvalidArgument, InvalidByteValue, InvalidHexDigit, InvalidIdentifier, MacroArgumentMismatch, MacroExpansionFailed, MissingOperand, MutableValue, NoAddress, NoValue, UnclosedParenthesis, UndefinedInstruction, UndefinedSymbols, UnterminatedMacro};
 
     type Errorable = Result<(), ParseError>;
 
@@ -58,7 +59,10 @@ mod parser_tests {
     #[test]
     fn test_undefined_value() {
         let mut p = Parser::new("push ham_cheese");
-        assert_eq!(p.parse(), Err(InvalidArgument { errors: vec![UndefinedSymbols] }));
+        assert_eq!(p.parse(), Err(InvalidArgument {
+            errors: vec![UndefinedSymbols { span: span(1, 6, "ham_cheese") }],
+            span: span(1, 1, "push"),
+        }));
     }
 
     #[test]
@@ -77,7 +81,7 @@ mod parser_tests {
     #[test]
     fn test_empty_program() {
         let mut p = Parser::new("");
-        assert_eq!(p.parse(), Err(EmptyProgram));
+        assert_eq!(p.parse(), Err(EmptyProgram { span: Span::default() }));
     }
 
     #[test]
@@ -88,4 +92,349 @@ mod parser_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_macro_expansion() -> Errorable {
+        let p: Parser = (*load_test_file("macros.asm")).try_into()?;
+
+        assert_eq!(p.ops[0..3], [Op::Push(10), Op::Push(20), Op::Add]);
+
+        // Each expansion has its own local labels.
+        let first_loop = p.symbols.offsets["__count_down_2_loop"];
+        let second_loop = p.symbols.offsets["__count_down_3_loop"];
+
+        assert_eq!(p.ops[6], Op::JumpNotZero(first_loop));
+        assert_eq!(p.ops[11], Op::JumpNotZero(second_loop));
+        assert_ne!(first_loop, second_loop);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_macro_expression_arguments() -> Errorable {
+        let source = ".macro p a\n push a\n.endmacro\n.const BUF = 0x10\n.var counter 5\np -1\np (1+2)\np BUF + 4\np &counter\np 2 * (3 - 1)";
+        let mut p = Parser::new(source);
+        p.parse()?;
+
+        assert_eq!(p.ops, vec![Op::Push(0xFFFF), Op::Push(3), Op::Push(0x14), Op::Push(DATA_START), Op::Push(4)]);
+
+        // Each operand is an argument, and a spaced minus sign starts a negative argument.
+        let mut p = Parser::new(".macro pair a b\n push a\n push b\n.endmacro\npair 10 -2\npair (1) 10 - 2\npair -1 ~0");
+        p.parse()?;
+        assert_eq!(p.ops, vec![Op::Push(10), Op::Push(0xFFFE), Op::Push(1), Op::Push(8), Op::Push(0xFFFF), Op::Push(0xFFFF)]);
+
+        let mut p = Parser::new(".macro pair a b\n push a\n push b\n.endmacro\npair 1 + 2");
+        assert!(matches!(p.parse(), Err(MacroArgumentMismatch { expected: 2, given: 1, .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_macro_errors() {
+        let mut p = Parser::new(".macro twice a\n push a\n push a\n");
+        assert_eq!(p.parse(), Err(UnterminatedMacro { name: "twice".into(), span: span(1, 1, ".macro") }));
+
+        let mut p = Parser::new(".macro twice a\n push a\n push a\n.endmacro\ntwice");
+        assert_eq!(p.parse(), Err(MacroArgumentMismatch {
+            name: "twice".into(),
+            expected: 1,
+            given: 0,
+            span: span(5, 1, "twice"),
+        }));
+
+        // Errors point to both the macro body and the call site.
+        let mut p = Parser::new(".macro twice a\n push a\n push b\n.endmacro\n\n  twice 5");
+        assert_eq!(p.parse(), Err(MacroExpansionFailed {
+            name: "twice".into(),
+            error: Box::new(InvalidArgument {
+                errors: vec![UndefinedSymbols { span: span(3, 7, "b") }],
+                span: span(3, 2, "push"),
+            }),
+            span: span(6, 3, "twice"),
+        }));
+    }
+
+    #[test]
+    fn test_scanner_error_location() {
+        let mut p = Parser::new("push 1\n  push 0xFFFFF");
+        let error = p.parse().expect_err("hex digit should be invalid");
+
+        assert_eq!(error, InvalidHexDigit { span: span(2, 8, "0xFFFFF") });
+    }
+
+    #[test]
+    fn test_render_error() {
+        let source = "push 1\npush ham_cheese\n";
+        let mut p = Parser::new(source);
+        let error = p.parse().expect_err("symbol should be undefined");
+
+        assert_eq!(error.render(source), [
+            "error: symbol 'ham_cheese' is not defined",
+            " --> line 2, column 6",
+            "  |",
+            "2 | push ham_cheese",
+            "  |      ^^^^^^^^^^",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_render_error_after_unicode() {
+        let source = "push 'é' + missing";
+        let mut p = Parser::new(source);
+        let error = p.parse().expect_err("symbol should be undefined");
+
+        // The column counts characters, not bytes, so the carets are under the lexeme.
+        assert_eq!(error, InvalidArgument { errors: vec![UndefinedSymbols { span: span(1, 12, "missing") }], span: span(1, 1, "push") });
+        assert_eq!(error.render(source).lines().last(), Some("  |            ^^^^^^^"));
+    }
+
+    #[test]
+    fn test_parse_with_recovery() {
+        let source = r"
+            push 0xFFFFF
+            jump start
+            pusj 1
+        start:
+            push missing
+            halt
+            push 2
+        unused:
+        ";
+
+        let diagnostics = Parser::new(source).parse_with_recovery();
+
+        assert_eq!(diagnostics.errors, [
+            InvalidHexDigit { span: span(2, 18, "0xFFFFF") },
+            UndefinedInstruction { name: "pusj".into(), span: span(4, 13, "pusj") },
+            InvalidArgument {
+                errors: vec![UndefinedSymbols { span: span(6, 18, "missing") }],
+                span: span(6, 13, "push"),
+            },
+        ]);
+
+        assert_eq!(diagnostics.warnings, [
+            ParseWarning::UnreachableCode { after: "jump".into(), span: span(4, 13, "pusj") },
+            ParseWarning::UnreachableCode { after: "halt".into(), span: span(8, 13, "push") },
+            ParseWarning::UnusedLabel { name: "unused".into(), span: span(9, 9, "unused:") },
+        ]);
+
+        // The fail-fast parser only reports the first error.
+        let mut p = Parser::new(source);
+        assert_eq!(p.parse(), Err(InvalidHexDigit { span: span(2, 18, "0xFFFFF") }));
+    }
+
+    #[test]
+    fn test_macro_errors_with_recovery() {
+        let source = ".macro twice a\n push a\n push a\n.endmacro\ntwice\ntwice 1 2\ntwice 3";
+
+        let diagnostics = Parser::new(source).parse_with_recovery();
+        assert_eq!(diagnostics.errors.len(), 2);
+
+        let mut p = Parser::new(source);
+        assert!(p.parse().is_err());
+    }
+
+    #[test]
+    fn test_include() -> Errorable {
+        let resolver = resolver(&[
+            ("lib/math.asm", ".include \"consts.asm\"\n.macro add_ten\n push ten\n add\n.endmacro"),
+            ("lib/consts.asm", ".value ten 10"),
+        ]);
+
+        let mut p = Parser::new(".include \"lib/math.asm\"\npush 5\nadd_ten").with_resolver(resolver);
+        p.parse()?;
+
+        assert_eq!(p.ops, [Op::Push(5), Op::Push(10), Op::Add]);
+        assert!(p.sources.contains_key("lib/consts.asm"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let resolver = resolver(&[
+            ("a.asm", ".include \"b.asm\""),
+            ("b.asm", ".include \"a.asm\""),
+            ("bad.asm", "push 1\npush cheese"),
+        ]);
+
+        let source = ".include \"a.asm\"\n.include \"nope.asm\"\n.include \"bad.asm\"";
+        let mut p = Parser::new(source).with_resolver(resolver);
+
+        let diagnostics = p.parse_with_recovery();
+
+        assert_eq!(diagnostics.errors, [
+            IncludeNotFound { path: "nope.asm".into(), span: span(2, 1, ".include") },
+            IncludeCycle { path: "a.asm".into(), span: Span { file: Some("b.asm".into()), ..span(1, 1, ".include") } },
+            InvalidArgument {
+                errors: vec![UndefinedSymbols { span: Span { file: Some("bad.asm".into()), ..span(2, 6, "cheese") } }],
+                span: Span { file: Some("bad.asm".into()), ..span(2, 1, "push") },
+            },
+        ]);
+
+        // Errors in the included files point to the source code of that file.
+        let text = diagnostics.render(&p);
+        assert!(text.contains("--> bad.asm, line 2, column 6\n  |\n2 | push cheese\n"));
+    }
+
+    #[test]
+    fn test_constant_expressions() -> Errorable {
+        let source = "
+.const BUF = 0x5000
+.const LEN = 4
+.value PACKED_PTR 0x5100
+
+start:
+push BUF+4
+load (PACKED_PTR + LEN*2)
+push 'A'
+push 1 << 3
+push ~0 >> 12
+jump start + 2
+";
+
+        let mut p = Parser::new(source);
+        p.parse()?;
+
+        assert_eq!(p.symbols.constants["LEN"], 4);
+
+        assert_eq!(p.ops, [
+            Op::Push(0x5004),
+            Op::Load(0x5108),
+            Op::Push(65),
+            Op::Push(8),
+            Op::Push(0xF),
+            Op::Jump(2),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_negative_literals() -> Errorable {
+        let mut p = Parser::new(".const MIN = -32768\npush -1\npush -(2 * 3)\npush MIN\npush --5");
+        p.parse()?;
+
+        assert_eq!(p.ops, [Op::Push(0xFFFF), Op::Push(0xFFFA), Op::Push(0x8000), Op::Push(5)]);
+
+        // A minus sign right before a value starts the next operand, when there is a space before it.
+        let mut p = Parser::new("send 1 -1
+send 3 - 1 2
+send 3-1 2
+send (3 -1) 2");
+        p.parse()?;
+
+        assert_eq!(p.ops, [Op::Send(1, 0xFFFF), Op::Send(2, 2), Op::Send(2, 2), Op::Send(2, 2)]);
+
+        // The negative operand is one operand too many, instead of being dropped.
+        let diagnostics = Parser::new("push 5 -1
+push 5 - 1
+add 2").parse_with_recovery();
+        assert_eq!(diagnostics.errors, [ExtraOperand { span: span(1, 8, "-") }, ExtraOperand { span: span(3, 5, "2") }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        let source = ".const MAX = 0xFFFF\npush MAX + 1\npush 3 - 4\npush 1 / 0\npush (1 + 2\npush 1 +\npush 1 << 16";
+        let diagnostics = Parser::new(source).parse_with_recovery();
+
+        let errors: Vec<ParseError> = diagnostics.errors.into_iter().flat_map(|e| match e {
+            InvalidArgument { errors, .. } => errors,
+            e => vec![e],
+        }).collect();
+
+        assert_eq!(errors, [
+            ExpressionOverflow { left: 0xFFFF, right: 1, span: span(2, 10, "+") },
+            ExpressionOverflow { left: 3, right: 4, span: span(3, 8, "-") },
+            DivisionByZero { span: span(4, 8, "/") },
+            UnclosedParenthesis { span: span(5, 6, "(") },
+            MissingOperand { span: span(6, 8, "+") },
+            ExpressionOverflow { left: 1, right: 16, span: span(7, 8, "<<") },
+        ]);
+    }
+
+    #[test]
+    fn test_data_directives() -> Errorable {
+        let p: Parser = (*load_test_file("arrays.asm")).try_into()?;
+
+        assert_eq!(p.symbols.offsets["nums"], 0);
+        assert_eq!(p.symbols.offsets["packed"], 3);
+        assert_eq!(p.symbols.offsets["buffer"], 8);
+
+        assert_eq!(p.symbols.words["packed"], [0x1234, 0x5600]);
+        assert_eq!(p.symbols.bytes(), [10, 20, 30, 0x1234, 0x5600, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(p.ops[0], Op::Load(DATA_START));
+        assert_eq!(p.ops[5], Op::Store(DATA_START + 8));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_directive_errors() {
+        let source = ".bytes big 1, 256\n.align 0\n.words\npush 1";
+        let diagnostics = Parser::new(source).parse_with_recovery();
+
+        assert_eq!(diagnostics.errors, [
+            InvalidByteValue { span: span(1, 1, ".bytes") },
+            InvalidAlignment { span: span(2, 1, ".align") },
+            InvalidIdentifier { span: span(3, 1, ".words") },
+        ]);
+    }
+
+    #[test]
+    fn test_data_segment_overflow() {
+        // The data segment is 0x1000 words, so the data fills it up exactly.
+        let mut p = Parser::new(".reserve a 0x0FFF\n.value v 1\npush 1");
+        assert_eq!(p.parse(), Ok(()));
+
+        let mut p = Parser::new(".reserve a 65535\n.reserve b 2\npush 1");
+        assert_eq!(p.parse(), Err(DataSegmentOverflow { span: span(1, 1, ".reserve") }));
+
+        let source = ".reserve a 0x0FFF\n.reserve b 2\n.string s \"é\"\n.value v 1\n.align 3\npush 1";
+        let diagnostics = Parser::new(source).parse_with_recovery();
+
+        assert_eq!(diagnostics.errors, [
+            DataSegmentOverflow { span: span(2, 1, ".reserve") },
+            DataSegmentOverflow { span: span(3, 1, ".string") },
+            DataSegmentOverflow { span: span(5, 1, ".align") },
+        ]);
+    }
+
+    #[test]
+    fn test_address_of() -> Errorable {
+        let p: Parser = (*load_test_file("variables.asm")).try_into()?;
+
+        assert_eq!(p.symbols.offsets["counter"], 1);
+
+        assert_eq!(p.ops[0], Op::Load(DATA_START + 1));
+        assert_eq!(p.ops[1], Op::Push(5));
+        assert_eq!(p.ops[4], Op::Load(DATA_START + 1));
+        assert_eq!(p.ops[5], Op::Push(DATA_START));
+
+        let source = ".const MAX = 10\nstart:\npush &MAX\npush *start\npush &nope\n.var v 1\npush *v";
+        let diagnostics = Parser::new(source).parse_with_recovery();
+
+        assert_eq!(diagnostics.errors, [
+            InvalidArgument { errors: vec![NoAddress { span: span(3, 7, "MAX") }], span: span(3, 1, "push") },
+            InvalidArgument { errors: vec![NoValue { span: span(4, 7, "start") }], span: span(4, 1, "push") },
+            InvalidArgument { errors: vec![UndefinedSymbols { span: span(5, 7, "nope") }], span: span(5, 1, "push") },
+            InvalidArgument { errors: vec![MutableValue { span: span(7, 7, "v") }], span: span(7, 1, "push") },
+        ]);
+
+        Ok(())
+    }
+
+    fn resolver(files: &[(&str, &str)]) -> MemoryResolver {
+        let files: HashMap<String, String> = files.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        MemoryResolver::new(files)
+    }
+
+    fn span(line: usize, column: usize, lexeme: &str) -> Span {
+        Span { line, column, lexeme: lexeme.into(), file: None }
+    }
 }
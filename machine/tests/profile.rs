@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod profile_tests {
+    use machine::{Execute, LabelProfile, Listing, Machine, Parser, Profile, SymbolMap};
+
+    const SOURCE: &str = "\
+jump start
+
+countdown:
+    dec
+    dup
+    jump_not_zero countdown
+    return
+
+start:
+    push 3
+    call countdown
+    pop
+    jump end
+
+unused:
+    push 1
+
+end:
+    halt
+";
+
+    fn profile() -> (Profile, Parser) {
+        let parser: Parser = SOURCE.try_into().expect("cannot compile the test program");
+
+        let mut m: Machine = parser.clone().into();
+        m.profile = Some(Profile::new());
+        m.run().expect("cannot run the test program");
+
+        (m.profile.unwrap(), parser)
+    }
+
+    #[test]
+    fn test_counts_and_cycles() {
+        let (profile, _) = profile();
+
+        // The loop body runs once for each count.
+        assert_eq!(profile.count_at(2), 3);
+        assert_eq!(profile.count_at(4), 3);
+        assert_eq!(profile.cycles[&4], 6);
+
+        // The unused label is never reached.
+        assert_eq!(profile.count_at(15), 0);
+        assert_eq!(profile.total_cycles(), 22);
+    }
+
+    #[test]
+    fn test_by_label() {
+        let (profile, parser) = profile();
+        let labels = profile.by_label(&SymbolMap::new(&parser.symbols, &parser.layout));
+
+        assert_eq!(labels[0], LabelProfile { name: "countdown".into(), address: 2, count: 10, cycles: 13 });
+        assert_eq!(labels[1], LabelProfile { name: "start".into(), address: 7, count: 4, cycles: 7 });
+        assert!(labels.iter().all(|p| p.name != "unused"));
+    }
+
+    #[test]
+    fn test_lcov() {
+        let (profile, parser) = profile();
+        let lcov = profile.render_lcov(&Listing::from(&parser), "countdown.asm");
+
+        assert!(lcov.starts_with("TN:\nSF:countdown.asm\nDA:1,1\nDA:4,3\n"));
+        assert!(lcov.contains("DA:16,0\n"));
+        assert!(lcov.ends_with("LF:11\nLH:9\nend_of_record\n"));
+    }
+
+    #[test]
+    fn test_profiling_disabled_by_default() {
+        let mut m: Machine = SOURCE.try_into().map(|p: Parser| p.into()).unwrap();
+        m.run().expect("cannot run the test program");
+
+        assert_eq!(m.profile, None);
+    }
+}
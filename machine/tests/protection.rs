@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod protection_tests {
+    use machine::{load_test_source, load_test_program, Execute, Machine, MemoryLayout, Op, RuntimeError, CALL_STACK_START, DATA_START, STACK_START};
+    use machine::RuntimeError::{ExecuteFault, ReadFault, WriteFault};
+
+    fn run_protected(source: &str) -> Result<Machine, RuntimeError> {
+        let mut m = load_test_source(source);
+        m.is_protected = true;
+        m.run()?;
+
+        Ok(m)
+    }
+
+    #[test]
+    fn test_code_is_read_only() {
+        assert_eq!(run_protected("push 5\nstore 1").err(), Some(WriteFault { pc: 2, address: 1 }));
+        assert!(run_protected("load 0\n.var a\npush 1\nstore a").is_ok());
+
+        // Without protection, the program can overwrite itself.
+        let mut m: Machine = vec![Op::Push(5), Op::Store(1)].into();
+        assert_eq!(m.run(), Ok(()));
+        assert_eq!(m.mem.get(1), 5);
+    }
+
+    #[test]
+    fn test_stacks_are_off_limits() {
+        let source = format!("push 1\nload {}", STACK_START);
+        assert_eq!(run_protected(&source).err(), Some(ReadFault { pc: 2, address: STACK_START }));
+
+        let source = format!("push 7\npush {}\nstore_i", CALL_STACK_START);
+        assert_eq!(run_protected(&source).err(), Some(WriteFault { pc: 4, address: CALL_STACK_START }));
+
+        // The stack operations can still use the stacks.
+        let mut m: Machine = load_test_program("factorial.asm");
+        m.is_protected = true;
+        assert_eq!(m.run(), Ok(()));
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        // The last word is in the heap, but the next word is past the end of the memory.
+        let end = MemoryLayout::default().size() as u16;
+        let source = format!("push 1\npush 2\npush {}\nwrite 2", end - 1);
+
+        assert_eq!(run_protected(&source).err(), Some(WriteFault { pc: 6, address: end }));
+        assert_eq!(run_protected(&format!("load {}", end)).err(), Some(ReadFault { pc: 0, address: end }));
+    }
+
+    #[test]
+    fn test_execute_outside_of_code() {
+        let source = format!("jump {}", DATA_START);
+        assert_eq!(run_protected(&source).err(), Some(ExecuteFault { pc: DATA_START }));
+    }
+
+    #[test]
+    fn test_strings_are_checked() {
+        let source = format!(".string msg \"hi\"\npush &msg\npush {}\nstrcmp", STACK_START);
+        assert_eq!(run_protected(&source).err(), Some(ReadFault { pc: 4, address: STACK_START }));
+
+        assert!(run_protected(".string msg \"hi\"\npush &msg\nstrlen").is_ok());
+    }
+}
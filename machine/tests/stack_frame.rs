@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod stack_frame_tests {
+    use machine::{load_test_program, Execute, Machine, Op, RuntimeError, CALL_STACK_START};
+    use machine::Register::{BP, FP, SP};
+    use machine::RuntimeError::{IndexOutOfBounds, MissingStackFrame};
+
+    type Errorable = Result<(), RuntimeError>;
+
+    #[test]
+    fn test_recursive_factorial() -> Errorable {
+        let mut m: Machine = load_test_program("factorial.asm");
+        m.run()?;
+
+        assert_eq!(m.mem.read_stack(1), [120]);
+        assert_eq!(m.stack().len(), 1);
+        assert_eq!(m.reg.get(FP), CALL_STACK_START - 1);
+        assert_eq!(m.reg.get(BP), CALL_STACK_START - 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_sum() -> Errorable {
+        let mut m: Machine = load_test_program("tree-sum.asm");
+        m.run()?;
+
+        assert_eq!(m.mem.read_stack(2), [28, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_layout() -> Errorable {
+        let mut m: Machine = vec![
+            Op::Push(7),
+            Op::Push(8),
+            Op::Call(7),
+            Op::Halt,
+            Op::Enter(2),
+            Op::LoadArg(1),
+            Op::StoreLocal(1),
+            Op::Push(9),
+            Op::StoreArg(0),
+            Op::LoadLocal(1),
+            Op::Leave,
+        ].into();
+
+        let sp = m.reg.get(SP);
+
+        for _ in 0..4 {
+            m.tick()?;
+        }
+
+        // [return address, caller's BP, SP at entry, locals...]
+        assert_eq!(m.reg.get(BP), CALL_STACK_START + 2);
+        assert_eq!(m.mem.read(CALL_STACK_START, 5), [5, CALL_STACK_START - 1, sp + 2, 0, 0]);
+
+        for _ in 0..5 {
+            m.tick()?;
+        }
+
+        assert_eq!(m.mem.get(CALL_STACK_START + 4), 7);
+        assert_eq!(m.mem.read_stack(3), [7, 9, 7]);
+
+        // Leaving the frame keeps the return address for `return`.
+        m.tick()?;
+        assert_eq!(m.reg.get(FP), CALL_STACK_START);
+        assert_eq!(m.reg.get(BP), CALL_STACK_START - 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_errors() {
+        let mut m: Machine = vec![Op::LoadLocal(0)].into();
+        assert_eq!(m.run(), Err(MissingStackFrame));
+
+        let mut m: Machine = vec![Op::Leave].into();
+        assert_eq!(m.run(), Err(MissingStackFrame));
+
+        let mut m: Machine = vec![Op::Enter(1), Op::LoadLocal(1)].into();
+        assert_eq!(m.run(), Err(IndexOutOfBounds { index: 1, len: 1 }));
+
+        let mut m: Machine = vec![Op::Push(1), Op::Enter(0), Op::LoadArg(1)].into();
+        assert_eq!(m.run(), Err(IndexOutOfBounds { index: 1, len: 1 }));
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use machine::{Execute, Machine, Op, RuntimeError, WithStringManager};
+    use machine::{run_test_source, Execute, Machine, Op, RuntimeError, WithStringManager, DATA_START};
+    use machine::Event::Print;
 
     /// Loads string manually using the Load instruction.
     /// Note that the LoadString instruction is a more convenient alternative.
@@ -24,4 +25,77 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_print_unicode() -> Result<(), RuntimeError> {
+        let m = run_test_source(".string msg \"héllo, 世界 🦀\"\nload_string msg\nprint")?;
+        assert_eq!(m.events, [Print { text: "héllo, 世界 🦀".into() }]);
+
+        // The crab takes up a surrogate pair.
+        let mut m = Machine::new();
+        let ptr = m.mem.string().add_str("🦀");
+        assert_eq!(m.mem.read(ptr, 3), [0xD83E, 0xDD80, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_utf16_data() -> Result<(), RuntimeError> {
+        // Strings in arrays and the value of a string are stored as UTF-16 as well.
+        let m = run_test_source(".words w \"🦀!\"\n.string s \"🦀\"\npush *s")?;
+        assert_eq!(m.mem.read(DATA_START, 3), [0xD83E, 0xDD80, '!' as u16]);
+        assert_eq!(m.mem.read_stack(1), [0xD83E]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strlen() -> Result<(), RuntimeError> {
+        let m = run_test_source(".string a \"hello\"\n.string b \"\"\n.string c \"🦀!\"\npush &a\nstrlen\npush &b\nstrlen\npush &c\nstrlen")?;
+        assert_eq!(m.mem.read_stack(3), [5, 0, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strcmp() -> Result<(), RuntimeError> {
+        let source = ".string a \"apple\"\n.string b \"apples\"\n.string c \"banana\"\n\
+            push &a\npush &a\nstrcmp\n\
+            push &a\npush &b\nstrcmp\n\
+            push &c\npush &a\nstrcmp";
+
+        let m = run_test_source(source)?;
+        assert_eq!(m.mem.read_stack(3), [0, -1i16 as u16, 1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_numbers() -> Result<(), RuntimeError> {
+        let source = ".string label \"total: \"\n\
+            push 0\nload_string label\npush 1234\nitoa\nprint\n\
+            push 0\npush 65535\nitoa\nprint\n\
+            push 0\npush 65535\nitoa_s\nprint\n\
+            push 0\npush 0\nitoa\nprint";
+
+        let m = run_test_source(source)?;
+
+        assert_eq!(m.events, [
+            Print { text: "total: 1234".into() },
+            Print { text: "65535".into() },
+            Print { text: "-1".into() },
+            Print { text: "0".into() },
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strings_on_heap() -> Result<(), RuntimeError> {
+        // Build "hi" on the heap, then measure it.
+        let m = run_test_source("alloc 3\ndup\npush 'h'\nswap\nstore_i_inc\npush 'i'\nswap\nstore_i\nstrlen")?;
+        assert_eq!(m.mem.read_stack(1), [2]);
+
+        Ok(())
+    }
 }
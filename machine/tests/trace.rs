@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod trace_tests {
+    use std::sync::{Arc, Mutex};
+    use machine::{Execute, Machine, MemoryWrite, Parser, RingBuffer, TraceRecord, Tracer, CALL_STACK_START, DATA_START};
+    use machine::cli::JsonLinesWriter;
+
+    const SOURCE: &str = "\
+.var total
+jump start
+
+double:
+    dup
+    add
+    return
+
+start:
+    push 3
+    call double
+    store total
+";
+
+    fn machine() -> Machine {
+        let parser: Parser = SOURCE.try_into().expect("cannot compile the test program");
+        parser.into()
+    }
+
+    #[test]
+    fn test_trace_records() {
+        let records = Arc::new(Mutex::new(Vec::<TraceRecord>::new()));
+
+        let mut m = machine();
+        m.tracer = Tracer::new(records.clone());
+        m.run().expect("cannot run the test program");
+
+        let records = records.lock().unwrap();
+        let ops: Vec<&str> = records.iter().map(|r| r.op.as_str()).collect();
+        assert_eq!(ops, ["jump 5", "push 3", "call 2", "dup", "add", "return", "store 4096"]);
+
+        let push = &records[1];
+        assert_eq!(push.pc, 5);
+        assert_eq!(push.stack_before, [] as [u16; 0]);
+        assert_eq!(push.stack_after, [3]);
+
+        // The return address is written to the call stack.
+        let call = &records[2];
+        assert_eq!(call.pc, 7);
+        assert_eq!(call.writes, [MemoryWrite { address: CALL_STACK_START, value: 8 }]);
+
+        let store = &records[6];
+        assert_eq!(store.stack_before, [6]);
+        assert_eq!(store.stack_after, [] as [u16; 0]);
+        assert_eq!(store.writes, [MemoryWrite { address: DATA_START, value: 6 }]);
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_recent_records() {
+        let buffer = Arc::new(Mutex::new(RingBuffer::new(2)));
+
+        let mut m = machine();
+        m.tracer = Tracer::new(buffer.clone());
+        m.run().expect("cannot run the test program");
+
+        let records = buffer.lock().unwrap().drain();
+        let ops: Vec<&str> = records.iter().map(|r| r.op.as_str()).collect();
+        assert_eq!(ops, ["return", "store 4096"]);
+        assert!(buffer.lock().unwrap().records.is_empty());
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let writer = Arc::new(Mutex::new(JsonLinesWriter::new(Vec::<u8>::new())));
+
+        let mut m = machine();
+        m.tracer = Tracer::new(writer.clone());
+        m.run().expect("cannot run the test program");
+        writer.lock().unwrap().finish().expect("cannot flush the trace");
+
+        let writer = writer.lock().unwrap();
+        let text = String::from_utf8(writer.get_ref().clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[1], r#"{"pc":5,"op":"push 3","stack_before":[],"stack_after":[3],"writes":[],"messages":[]}"#);
+    }
+
+    #[test]
+    fn test_tracer_disabled_by_default() {
+        let mut m = machine();
+        m.run().expect("cannot run the test program");
+
+        assert!(!m.tracer.is_enabled());
+        assert_eq!(m.mem.journal, None);
+    }
+}